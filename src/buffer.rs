@@ -0,0 +1,207 @@
+use crate::{
+    context::{Context, GlObject},
+    gl_error::GlError,
+    stats::ResourceKind,
+};
+use gl46::{
+    BufferTargetARB, GLbitfield, GL_DYNAMIC_STORAGE_BIT, GL_MAP_COHERENT_BIT,
+    GL_MAP_PERSISTENT_BIT, GL_MAP_WRITE_BIT,
+};
+use std::{
+    ffi::{c_uint, c_void},
+    marker::PhantomData,
+    ptr::null,
+    rc::Rc,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BufferError {
+    #[error("Buffer storage is static, create the buffer as dynamic to update it")]
+    StaticStorage,
+    #[error(transparent)]
+    Gl(#[from] GlError),
+    #[error("Update range of {count} elements at {offset} exceeds {len} elements")]
+    RangeOutOfBounds {
+        count: usize,
+        len: usize,
+        offset: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) enum BufferStorage {
+    #[default]
+    Static,
+    Dynamic,
+    PersistentWrite,
+}
+
+impl BufferStorage {
+    fn flags(self) -> GLbitfield {
+        match self {
+            Self::Static => GLbitfield(0),
+            Self::Dynamic => GL_DYNAMIC_STORAGE_BIT,
+            Self::PersistentWrite => GL_MAP_WRITE_BIT | GL_MAP_PERSISTENT_BIT | GL_MAP_COHERENT_BIT,
+        }
+    }
+}
+
+// Owns the GL buffer. Shared, so a mesh keeps the buffers it reads from
+// alive after their `Vertices` or `Indices` are dropped.
+pub(crate) struct BufferObject {
+    gl: Rc<Context>,
+    id: c_uint,
+}
+
+impl BufferObject {
+    pub(crate) fn id(&self) -> c_uint {
+        self.id
+    }
+}
+
+impl Drop for BufferObject {
+    fn drop(&mut self) {
+        self.gl.delete_later(GlObject::Buffer(self.id));
+    }
+}
+
+// Immutable storage created through DSA, so creating or updating a buffer
+// never touches the context's binding points.
+pub(crate) struct Buffer<T> {
+    len: usize,
+    object: Rc<BufferObject>,
+    storage: BufferStorage,
+    _p: PhantomData<T>,
+}
+
+impl<T> Buffer<T> {
+    #[track_caller]
+    pub(crate) fn bind(&self, target: BufferTargetARB) -> Result<(), GlError> {
+        self.object.gl.bind_buffer(target, self.object.id)
+    }
+
+    #[track_caller]
+    fn create(
+        gl: &Rc<Context>,
+        len: usize,
+        data: *const c_void,
+        storage: BufferStorage,
+    ) -> Result<Self, GlError> {
+        let mut id = 0;
+
+        // Zero-sized storage is an error, so empty buffers get a single byte.
+        let size = (len * std::mem::size_of::<T>()).max(1);
+
+        unsafe {
+            gl.CreateBuffers(1, &mut id);
+        }
+
+        gl.check("glCreateBuffers")?;
+        gl.register(ResourceKind::Buffer, id, len * std::mem::size_of::<T>());
+
+        // Built before the storage so a buffer without storage is still
+        // deleted.
+        let buffer = Self {
+            len,
+            object: Rc::new(BufferObject { gl: gl.clone(), id }),
+            storage,
+            _p: PhantomData,
+        };
+
+        unsafe {
+            gl.NamedBufferStorage(id, size as _, data, storage.flags());
+        }
+
+        gl.check("glNamedBufferStorage")?;
+
+        Ok(buffer)
+    }
+
+    pub(crate) fn id(&self) -> c_uint {
+        self.object.id
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    // The storage flags of a persistent buffer double as its access flags.
+    // Deleting the buffer unmaps it.
+    pub(crate) fn map_persistent(&self) -> *mut T {
+        unsafe {
+            self.object.gl.MapNamedBufferRange(
+                self.object.id,
+                0,
+                (self.len * std::mem::size_of::<T>()) as _,
+                self.storage.flags(),
+            )
+        }
+        .cast()
+    }
+
+    #[track_caller]
+    pub(crate) fn new(
+        gl: &Rc<Context>,
+        data: &[T],
+        storage: BufferStorage,
+    ) -> Result<Self, GlError> {
+        let data_ptr = if data.is_empty() {
+            null()
+        } else {
+            data.as_ptr().cast()
+        };
+
+        Self::create(gl, data.len(), data_ptr, storage)
+    }
+
+    pub(crate) fn object(&self) -> Rc<BufferObject> {
+        self.object.clone()
+    }
+
+    #[track_caller]
+    pub(crate) fn uninitialized(
+        gl: &Rc<Context>,
+        len: usize,
+        storage: BufferStorage,
+    ) -> Result<Self, GlError> {
+        Self::create(gl, len, null(), storage)
+    }
+
+    #[track_caller]
+    pub(crate) fn set_label(&self, label: &str) -> Result<(), GlError> {
+        self.object
+            .gl
+            .object_label(ResourceKind::Buffer, self.object.id, label)
+    }
+
+    #[track_caller]
+    pub(crate) fn update(&self, offset: usize, data: &[T]) -> Result<(), BufferError> {
+        if self.storage != BufferStorage::Dynamic {
+            return Err(BufferError::StaticStorage);
+        }
+
+        if offset
+            .checked_add(data.len())
+            .is_none_or(|end| end > self.len)
+        {
+            return Err(BufferError::RangeOutOfBounds {
+                count: data.len(),
+                len: self.len,
+                offset,
+            });
+        }
+
+        unsafe {
+            self.object.gl.NamedBufferSubData(
+                self.object.id,
+                (offset * std::mem::size_of::<T>()) as _,
+                std::mem::size_of_val(data) as _,
+                data.as_ptr().cast(),
+            );
+        }
+
+        self.object.gl.check("glNamedBufferSubData")?;
+
+        Ok(())
+    }
+}
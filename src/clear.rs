@@ -0,0 +1,126 @@
+use crate::{
+    math::{IVec4, UVec4, Vec4},
+    Color,
+};
+use gl46::{
+    GLbitfield, GlFns, GL_COLOR, GL_COLOR_BUFFER_BIT, GL_DEPTH_BUFFER_BIT, GL_STENCIL_BUFFER_BIT,
+};
+use std::ops::BitOr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ClearBuffers {
+    pub color: bool,
+    pub depth: bool,
+    pub stencil: bool,
+}
+
+impl ClearBuffers {
+    pub const ALL: Self = Self {
+        color: true,
+        depth: true,
+        stencil: true,
+    };
+
+    pub const COLOR: Self = Self {
+        color: true,
+        depth: false,
+        stencil: false,
+    };
+
+    pub const DEPTH: Self = Self {
+        color: false,
+        depth: true,
+        stencil: false,
+    };
+
+    pub const STENCIL: Self = Self {
+        color: false,
+        depth: false,
+        stencil: true,
+    };
+
+    pub(crate) fn gl_mask(self) -> GLbitfield {
+        let mut mask = GLbitfield(0);
+
+        if self.color {
+            mask |= GL_COLOR_BUFFER_BIT;
+        }
+
+        if self.depth {
+            mask |= GL_DEPTH_BUFFER_BIT;
+        }
+
+        if self.stencil {
+            mask |= GL_STENCIL_BUFFER_BIT;
+        }
+
+        mask
+    }
+}
+
+impl BitOr for ClearBuffers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self {
+            color: self.color || rhs.color,
+            depth: self.depth || rhs.depth,
+            stencil: self.stencil || rhs.stencil,
+        }
+    }
+}
+
+pub trait ClearColor: sealed::Sealed {}
+
+impl ClearColor for Color {}
+
+impl ClearColor for IVec4 {}
+
+impl ClearColor for UVec4 {}
+
+impl ClearColor for Vec4 {}
+
+mod sealed {
+    use super::*;
+
+    // Returns the name of the GL call it made, for the caller to check.
+    pub trait Sealed {
+        fn clear_buffer(&self, gl: &GlFns, draw_buffer: usize) -> &'static str;
+    }
+
+    impl Sealed for Color {
+        fn clear_buffer(&self, gl: &GlFns, draw_buffer: usize) -> &'static str {
+            Vec4::from(*self).clear_buffer(gl, draw_buffer)
+        }
+    }
+
+    impl Sealed for IVec4 {
+        fn clear_buffer(&self, gl: &GlFns, draw_buffer: usize) -> &'static str {
+            unsafe {
+                gl.ClearBufferiv(GL_COLOR, draw_buffer as _, self.as_ref().as_ptr());
+            }
+
+            "glClearBufferiv"
+        }
+    }
+
+    impl Sealed for UVec4 {
+        fn clear_buffer(&self, gl: &GlFns, draw_buffer: usize) -> &'static str {
+            unsafe {
+                gl.ClearBufferuiv(GL_COLOR, draw_buffer as _, self.as_ref().as_ptr());
+            }
+
+            "glClearBufferuiv"
+        }
+    }
+
+    impl Sealed for Vec4 {
+        fn clear_buffer(&self, gl: &GlFns, draw_buffer: usize) -> &'static str {
+            unsafe {
+                gl.ClearBufferfv(GL_COLOR, draw_buffer as _, self.as_ref().as_ptr());
+            }
+
+            "glClearBufferfv"
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{math::Vec4, vertices::VertexAttribute};
+use std::str::FromStr;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ColorError {
+    #[error("Invalid hex color {0:?}")]
+    InvalidHex(String),
+}
+
+// Components are stored in linear space with straight (non-premultiplied)
+// alpha unless `premultiplied` was called.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Self = Self::linear(0.0, 0.0, 0.0, 1.0);
+    pub const TRANSPARENT: Self = Self::linear(0.0, 0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::linear(1.0, 1.0, 1.0, 1.0);
+
+    pub fn from_hex(hex: &str) -> Result<Self, ColorError> {
+        let error = || ColorError::InvalidHex(hex.to_owned());
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(error());
+        }
+
+        let channel = |i: usize, len: usize| {
+            let value = u8::from_str_radix(&digits[i * len..(i + 1) * len], 16).unwrap();
+
+            if len == 1 {
+                value * 0x11
+            } else {
+                value
+            }
+        };
+
+        match digits.len() {
+            3 => Ok(Self::srgb8(
+                channel(0, 1),
+                channel(1, 1),
+                channel(2, 1),
+                0xff,
+            )),
+            4 => Ok(Self::srgb8(
+                channel(0, 1),
+                channel(1, 1),
+                channel(2, 1),
+                channel(3, 1),
+            )),
+            6 => Ok(Self::srgb8(
+                channel(0, 2),
+                channel(1, 2),
+                channel(2, 2),
+                0xff,
+            )),
+            8 => Ok(Self::srgb8(
+                channel(0, 2),
+                channel(1, 2),
+                channel(2, 2),
+                channel(3, 2),
+            )),
+            _ => Err(error()),
+        }
+    }
+
+    // Hue is in degrees. HSV is defined on the sRGB-encoded values, as in
+    // every color picker.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let m = value - chroma;
+
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+
+        Self::srgb(r + m, g + m, b + m, alpha)
+    }
+
+    pub const fn linear(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub fn premultiplied(self) -> Self {
+        Self::linear(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    pub fn srgb(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self::linear(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+    }
+
+    pub fn srgb8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let unorm = |c: u8| c as f32 / 255.0;
+
+        Self::srgb(unorm(r), unorm(g), unorm(b), unorm(a))
+    }
+
+    pub fn to_hsv(self) -> [f32; 4] {
+        let [r, g, b, a] = self.to_srgb();
+        let max = r.max(g).max(b);
+        let chroma = max - r.min(g).min(b);
+
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+        [hue, saturation, max, a]
+    }
+
+    pub fn to_linear(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    pub fn to_srgb(self) -> [f32; 4] {
+        [
+            linear_to_srgb(self.r),
+            linear_to_srgb(self.g),
+            linear_to_srgb(self.b),
+            self.a,
+        ]
+    }
+
+    pub fn to_srgb8(self) -> [u8; 4] {
+        self.to_srgb()
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    pub fn unpremultiplied(self) -> Self {
+        if self.a == 0.0 {
+            return Self::TRANSPARENT;
+        }
+
+        Self::linear(self.r / self.a, self.g / self.a, self.b / self.a, self.a)
+    }
+
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.a = alpha;
+
+        self
+    }
+}
+
+impl From<Color> for Vec4 {
+    fn from(color: Color) -> Self {
+        Vec4::from_array(color.to_linear())
+    }
+}
+
+impl From<Color> for VertexAttribute<f32, 4> {
+    fn from(color: Color) -> Self {
+        VertexAttribute::new(color.to_linear())
+    }
+}
+
+impl FromStr for Color {
+    type Err = ColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: impl IntoIterator<Item = f32>, b: impl IntoIterator<Item = f32>) {
+        for (a, b) in a.into_iter().zip(b) {
+            assert!((a - b).abs() < 1e-4, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        for i in 0..=255 {
+            let c = i as f32 / 255.0;
+
+            assert_close([linear_to_srgb(srgb_to_linear(c))], [c]);
+        }
+
+        assert_close([srgb_to_linear(0.5)], [0.21404]);
+        assert_close([linear_to_srgb(0.5)], [0.73536]);
+        assert_close([srgb_to_linear(0.02)], [0.02 / 12.92]);
+    }
+
+    #[test]
+    fn parses_hex() {
+        assert_eq!(
+            Color::from_hex("#ff8000").unwrap().to_srgb8(),
+            [0xff, 0x80, 0x00, 0xff]
+        );
+        assert_eq!(
+            "f80c".parse::<Color>().unwrap().to_srgb8(),
+            [0xff, 0x88, 0x00, 0xcc]
+        );
+        assert_eq!(
+            Color::from_hex("#12345678").unwrap().to_srgb8(),
+            [0x12, 0x34, 0x56, 0x78]
+        );
+        assert_eq!(Color::from_hex("#fff").unwrap(), Color::WHITE);
+        assert!(Color::from_hex("#12345").is_err());
+        assert!(Color::from_hex("#gggggg").is_err());
+        assert!(Color::from_hex("#ffé").is_err());
+    }
+
+    #[test]
+    fn converts_hsv() {
+        assert_eq!(
+            Color::from_hsv(0.0, 1.0, 1.0, 1.0).to_srgb8(),
+            [255, 0, 0, 255]
+        );
+        assert_eq!(
+            Color::from_hsv(120.0, 1.0, 1.0, 1.0).to_srgb8(),
+            [0, 255, 0, 255]
+        );
+        assert_eq!(
+            Color::from_hsv(-120.0, 1.0, 0.6, 1.0).to_srgb8(),
+            [0, 0, 153, 255]
+        );
+
+        for hsv in [
+            [30.0, 0.5, 0.75, 1.0],
+            [200.0, 0.25, 1.0, 0.5],
+            [330.0, 1.0, 0.2, 0.0],
+        ] {
+            let [h, s, v, a] = hsv;
+
+            assert_close(Color::from_hsv(h, s, v, a).to_hsv(), hsv);
+        }
+
+        assert_close(
+            Color::from_hex("#808080").unwrap().to_hsv(),
+            [0.0, 0.0, 128.0 / 255.0, 1.0],
+        );
+    }
+
+    #[test]
+    fn premultiplies() {
+        let color = Color::linear(0.5, 0.25, 1.0, 0.5);
+
+        assert_close(color.premultiplied().to_linear(), [0.25, 0.125, 0.5, 0.5]);
+        assert_close(
+            color.premultiplied().unpremultiplied().to_linear(),
+            color.to_linear(),
+        );
+        assert_eq!(
+            Color::linear(1.0, 1.0, 1.0, 0.0)
+                .premultiplied()
+                .unpremultiplied(),
+            Color::TRANSPARENT
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use crate::{
+    gl_error::GlError,
+    math::{Mat4, Vec2},
+    pipeline::PipelineState,
+    program::{Program, ProgramError, UniformId, UniformType},
+    renderer::DrawError,
+};
+use std::{
+    ffi::c_uint,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SubmitError {
+    #[error(transparent)]
+    Draw(#[from] DrawError),
+    #[error("Draw recorded before any mesh was bound")]
+    NoMesh,
+    #[error("Command recorded before any program was used")]
+    NoProgram,
+    #[error(transparent)]
+    Program(#[from] ProgramError),
+    #[error("Mesh {0:?} was not passed to submit or no longer exists")]
+    UnknownMesh(MeshHandle),
+    #[error("Program {0:?} was not passed to submit or no longer exists")]
+    UnknownProgram(ProgramHandle),
+}
+
+// Handles name a resource without owning it, so they can be recorded on any
+// thread. They are resolved against the resources passed to `submit`. GL
+// reuses the names of deleted objects, so a handle also carries the
+// generation of its resource and never resolves to a later one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle {
+    pub(crate) generation: u64,
+    pub(crate) id: c_uint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProgramHandle {
+    pub(crate) generation: u64,
+    pub(crate) id: c_uint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Mat4(Mat4),
+    Vec2(Vec2),
+}
+
+impl UniformType for UniformValue {
+    #[track_caller]
+    fn set_uniform(&self, program: &Program, uniform_id: UniformId) -> Result<(), GlError> {
+        match self {
+            Self::Float(value) => value.set_uniform(program, uniform_id),
+            Self::Mat4(value) => value.set_uniform(program, uniform_id),
+            Self::Vec2(value) => value.set_uniform(program, uniform_id),
+        }
+    }
+}
+
+impl From<f32> for UniformValue {
+    fn from(value: f32) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<Mat4> for UniformValue {
+    fn from(value: Mat4) -> Self {
+        Self::Mat4(value)
+    }
+}
+
+impl From<Vec2> for UniformValue {
+    fn from(value: Vec2) -> Self {
+        Self::Vec2(value)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    BindMesh(MeshHandle),
+    Draw {
+        instances: usize,
+        range: Option<(usize, usize)>,
+    },
+    SetPipeline(PipelineState),
+    SetUniform {
+        name: Box<str>,
+        value: UniformValue,
+    },
+    UseProgram(ProgramHandle),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Packet {
+    commands: Vec<Command>,
+    key: u64,
+    order: u32,
+    sequence: usize,
+}
+
+// Commands are recorded into packets, each with a sort key. Sorting reorders
+// whole packets and keeps the commands inside a packet in recording order.
+// Equal keys fall back to the order of the buffer, then to recording order,
+// so merging the same buffers always gives the same result no matter which
+// thread finished first. State carries over from one packet to the next.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandBuffer {
+    order: u32,
+    packets: Vec<Packet>,
+}
+
+impl CommandBuffer {
+    pub fn begin(&mut self, key: u64) {
+        self.packets.push(Packet {
+            commands: Vec::new(),
+            key,
+            order: self.order,
+            sequence: self.packets.len(),
+        });
+    }
+
+    pub fn bind_mesh(&mut self, mesh: MeshHandle) {
+        self.push(Command::BindMesh(mesh));
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &Command> {
+        self.packets.iter().flat_map(|packet| &packet.commands)
+    }
+
+    pub fn draw(&mut self) {
+        self.push(Command::Draw {
+            instances: 1,
+            range: None,
+        });
+    }
+
+    pub fn draw_instanced(&mut self, instances: usize) {
+        self.push(Command::Draw {
+            instances,
+            range: None,
+        });
+    }
+
+    pub fn draw_range(&mut self, first: usize, count: usize) {
+        self.push(Command::Draw {
+            instances: 1,
+            range: Some((first, count)),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.iter().all(|packet| packet.commands.is_empty())
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets
+            .iter()
+            .map(|packet| packet.commands.len())
+            .sum()
+    }
+
+    pub fn merge(buffers: impl IntoIterator<Item = CommandBuffer>) -> Self {
+        let mut merged = Self::default();
+
+        for buffer in buffers {
+            merged.packets.extend(buffer.packets);
+        }
+
+        merged.sort();
+
+        merged
+    }
+
+    // Buffers recorded on different threads should get distinct orders, such
+    // as the index of the job that recorded them.
+    pub fn new(order: u32) -> Self {
+        Self {
+            order,
+            packets: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, command: Command) {
+        if self.packets.is_empty() {
+            self.begin(0);
+        }
+
+        if let Some(packet) = self.packets.last_mut() {
+            packet.commands.push(command);
+        }
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: &PipelineState) {
+        self.push(Command::SetPipeline(*pipeline));
+    }
+
+    pub fn set_uniform(&mut self, name: &str, value: impl Into<UniformValue>) {
+        self.push(Command::SetUniform {
+            name: name.into(),
+            value: value.into(),
+        });
+    }
+
+    pub fn sort(&mut self) {
+        self.packets
+            .sort_by_key(|packet| (packet.key, packet.order, packet.sequence));
+    }
+
+    pub fn use_program(&mut self, program: ProgramHandle) {
+        self.push(Command::UseProgram(program));
+    }
+}
+
+// Shared by every context, so a handle never matches a resource of another
+// renderer either.
+pub(crate) fn next_generation() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorded(order: u32, packets: &[(u64, u32)]) -> CommandBuffer {
+        let mut buffer = CommandBuffer::new(order);
+
+        for &(key, mesh) in packets {
+            buffer.begin(key);
+            buffer.bind_mesh(MeshHandle {
+                generation: 0,
+                id: mesh,
+            });
+            buffer.draw();
+        }
+
+        buffer
+    }
+
+    fn meshes(buffer: &CommandBuffer) -> Vec<c_uint> {
+        buffer
+            .commands()
+            .filter_map(|command| match command {
+                Command::BindMesh(mesh) => Some(mesh.id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generations_are_unique() {
+        let first = next_generation();
+        let second = next_generation();
+
+        assert_ne!(first, second);
+        assert_ne!(
+            MeshHandle {
+                generation: first,
+                id: 1,
+            },
+            MeshHandle {
+                generation: second,
+                id: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn is_send() {
+        fn assert_send<T: Send>() {}
+
+        assert_send::<CommandBuffer>();
+        assert_send::<Command>();
+    }
+
+    #[test]
+    fn records_in_order() {
+        let mut buffer = CommandBuffer::default();
+
+        buffer.use_program(ProgramHandle {
+            generation: 0,
+            id: 1,
+        });
+        buffer.set_uniform("scale", 2.0);
+        buffer.set_pipeline(&PipelineState::default());
+        buffer.bind_mesh(MeshHandle {
+            generation: 0,
+            id: 2,
+        });
+        buffer.draw_range(3, 6);
+
+        assert_eq!(buffer.len(), 5);
+        assert_eq!(
+            buffer.commands().cloned().collect::<Vec<_>>(),
+            [
+                Command::UseProgram(ProgramHandle {
+                    generation: 0,
+                    id: 1,
+                }),
+                Command::SetUniform {
+                    name: "scale".into(),
+                    value: UniformValue::Float(2.0),
+                },
+                Command::SetPipeline(PipelineState::default()),
+                Command::BindMesh(MeshHandle {
+                    generation: 0,
+                    id: 2,
+                }),
+                Command::Draw {
+                    instances: 1,
+                    range: Some((3, 6)),
+                },
+            ]
+        );
+        assert!(CommandBuffer::default().is_empty());
+    }
+
+    #[test]
+    fn sorts_packets_stably() {
+        let mut buffer = recorded(0, &[(2, 10), (1, 11), (2, 12), (0, 13)]);
+
+        buffer.sort();
+
+        assert_eq!(meshes(&buffer), [13, 11, 10, 12]);
+        assert_eq!(
+            buffer.commands().collect::<Vec<_>>()[..2],
+            [
+                &Command::BindMesh(MeshHandle {
+                    generation: 0,
+                    id: 13,
+                }),
+                &Command::Draw {
+                    instances: 1,
+                    range: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_deterministically() {
+        let first = recorded(0, &[(1, 10), (3, 11)]);
+        let second = recorded(1, &[(1, 20), (2, 21)]);
+        let third = recorded(2, &[(0, 30), (3, 31)]);
+
+        let merged = CommandBuffer::merge([first.clone(), second.clone(), third.clone()]);
+
+        assert_eq!(meshes(&merged), [30, 10, 20, 21, 11, 31]);
+        assert_eq!(CommandBuffer::merge([third, first, second]), merged);
+    }
+}
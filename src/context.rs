@@ -0,0 +1,701 @@
+use crate::{
+    debug::{debug_callback, DebugCallback},
+    gl_error::GlError,
+    pipeline::PipelineState,
+    primitive::Primitive,
+    reflection::ComponentType,
+    stats::{FrameStats, Registry, Resource, ResourceKind, ResourceUsage},
+};
+use gl46::{
+    BufferTargetARB, EnableCap, GLenum, GLsync, GlFns, VertexAttribPointerType, GL_ARRAY_BUFFER,
+    GL_DEBUG_OUTPUT, GL_DEBUG_OUTPUT_SYNCHRONOUS, GL_DEBUG_SOURCE_APPLICATION,
+    GL_ELEMENT_ARRAY_BUFFER, GL_FALSE, GL_PATCH_VERTICES,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ffi::{c_int, c_uint},
+    ops::Deref,
+    ptr::null,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct VertexAttribState {
+    pub(crate) buffer: c_uint,
+    pub(crate) component_type: ComponentType,
+    pub(crate) components: usize,
+    pub(crate) divisor: c_uint,
+    pub(crate) gl_type: VertexAttribPointerType,
+    pub(crate) offset: usize,
+    pub(crate) stride: usize,
+}
+
+#[derive(Default)]
+struct VertexArrayState {
+    // Every entry is an enabled array. A buffer of 0 marks an array whose
+    // buffer has been deleted and needs to be specified again.
+    attributes: HashMap<c_uint, VertexAttribState>,
+    element_buffer: Option<c_uint>,
+}
+
+pub(crate) enum GlObject {
+    Buffer(c_uint),
+    Framebuffer(c_uint),
+    Program(c_uint),
+    Query(c_uint),
+    Sync(GLsync),
+    Texture(c_uint),
+    VertexArray(c_uint),
+}
+
+impl GlObject {
+    fn resource(&self) -> Option<(ResourceKind, c_uint)> {
+        match *self {
+            Self::Buffer(id) => Some((ResourceKind::Buffer, id)),
+            Self::Framebuffer(id) => Some((ResourceKind::Framebuffer, id)),
+            Self::Program(id) => Some((ResourceKind::Program, id)),
+            Self::Query(id) => Some((ResourceKind::Query, id)),
+            Self::Sync(_) => None,
+            Self::Texture(id) => Some((ResourceKind::Texture, id)),
+            Self::VertexArray(id) => Some((ResourceKind::VertexArray, id)),
+        }
+    }
+}
+
+// `None` means the GL state is unknown, either because it was never set or
+// because the cache was invalidated.
+#[derive(Default)]
+struct StateCache {
+    buffers: HashMap<GLenum, c_uint>,
+    enabled: HashMap<GLenum, bool>,
+    patch_vertices: Option<c_int>,
+    pipeline: Option<PipelineState>,
+    program: Option<c_uint>,
+    textures: HashMap<c_uint, c_uint>,
+    vertex_array: Option<c_uint>,
+    vertex_arrays: HashMap<c_uint, VertexArrayState>,
+}
+
+impl StateCache {
+    fn current_vertex_array(&mut self) -> Option<&mut VertexArrayState> {
+        let vertex_array = self.vertex_array?;
+
+        Some(self.vertex_arrays.entry(vertex_array).or_default())
+    }
+}
+
+pub(crate) type LeakCallback = dyn FnMut(&Resource);
+
+// Shared by the renderer and every resource created through it. Resources
+// queue their objects for deletion instead of deleting them on drop, so
+// dropping one never calls into GL. The queue is only drained where the
+// context is known to be current: draws, `Renderer::collect_garbage` and
+// finally `lose`.
+pub(crate) struct Context {
+    debug_callback: RefCell<Option<Box<DebugCallback>>>,
+    deleted: RefCell<Vec<GlObject>>,
+    frame: Cell<FrameStats>,
+    gl: GlFns,
+    leak_callback: RefCell<Option<Box<LeakCallback>>>,
+    lost: Cell<bool>,
+    registry: RefCell<Registry>,
+    state: RefCell<StateCache>,
+}
+
+impl Context {
+    #[track_caller]
+    pub(crate) fn bind_buffer(
+        &self,
+        target: BufferTargetARB,
+        buffer: c_uint,
+    ) -> Result<(), GlError> {
+        let mut state = self.state.borrow_mut();
+
+        // The element array binding belongs to the bound vertex array.
+        let cached = if target == GL_ELEMENT_ARRAY_BUFFER {
+            state
+                .current_vertex_array()
+                .and_then(|vertex_array| vertex_array.element_buffer.replace(buffer))
+        } else {
+            state.buffers.insert(target, buffer)
+        };
+
+        if cached == Some(buffer) {
+            return Ok(());
+        }
+
+        self.count_state_change();
+
+        unsafe {
+            self.gl.BindBuffer(target, buffer);
+        }
+
+        self.check("glBindBuffer")
+    }
+
+    // Binding a range also replaces the generic binding of the target.
+    #[track_caller]
+    pub(crate) fn bind_buffer_range(
+        &self,
+        target: BufferTargetARB,
+        index: c_uint,
+        buffer: c_uint,
+        offset: usize,
+        size: usize,
+    ) -> Result<(), GlError> {
+        self.state.borrow_mut().buffers.insert(target, buffer);
+        self.count_state_change();
+
+        unsafe {
+            self.gl
+                .BindBufferRange(target, index, buffer, offset as _, size as _);
+        }
+
+        self.check("glBindBufferRange")
+    }
+
+    #[track_caller]
+    pub(crate) fn bind_texture(&self, unit: c_uint, texture: c_uint) -> Result<(), GlError> {
+        if self.state.borrow_mut().textures.insert(unit, texture) == Some(texture) {
+            return Ok(());
+        }
+
+        self.count_state_change();
+
+        unsafe {
+            self.gl.BindTextureUnit(unit, texture);
+        }
+
+        self.check("glBindTextureUnit")
+    }
+
+    #[track_caller]
+    pub(crate) fn bind_vertex_array(&self, vertex_array: c_uint) -> Result<(), GlError> {
+        if self.state.borrow_mut().vertex_array.replace(vertex_array) == Some(vertex_array) {
+            return Ok(());
+        }
+
+        self.count_state_change();
+        self.gl.BindVertexArray(vertex_array);
+        self.check("glBindVertexArray")
+    }
+
+    // Follows every GL call, named after the GL function. Everything on the
+    // way from the public operation is `#[track_caller]`, so the error points
+    // at the caller of that operation.
+    #[track_caller]
+    #[inline(always)]
+    pub(crate) fn check(&self, operation: &'static str) -> Result<(), GlError> {
+        #[cfg(all(feature = "checked-gl", debug_assertions))]
+        {
+            let code = unsafe { self.gl.GetError() };
+
+            if code != gl46::GL_NO_ERROR {
+                while unsafe { self.gl.GetError() } != gl46::GL_NO_ERROR {}
+
+                return Err(GlError {
+                    code: crate::gl_error::GlErrorCode::from_gl(code),
+                    location: std::panic::Location::caller(),
+                    operation,
+                });
+            }
+        }
+
+        #[cfg(not(all(feature = "checked-gl", debug_assertions)))]
+        let _ = operation;
+
+        Ok(())
+    }
+
+    // Deletes every queued object even if one fails, and reports the first
+    // failure.
+    #[track_caller]
+    pub(crate) fn collect_garbage(&self) -> Result<(), GlError> {
+        let deleted = std::mem::take(&mut *self.deleted.borrow_mut());
+        let mut result = Ok(());
+
+        for object in deleted {
+            let operation = unsafe {
+                match object {
+                    GlObject::Buffer(buffer) => {
+                        self.forget_buffer(buffer);
+                        self.gl.DeleteBuffers(1, &buffer);
+
+                        "glDeleteBuffers"
+                    }
+                    GlObject::Framebuffer(framebuffer) => {
+                        self.gl.DeleteFramebuffers(1, &framebuffer);
+
+                        "glDeleteFramebuffers"
+                    }
+                    GlObject::Program(program) => {
+                        self.forget_program(program);
+                        self.gl.DeleteProgram(program);
+
+                        "glDeleteProgram"
+                    }
+                    GlObject::Query(query) => {
+                        self.gl.DeleteQueries(1, &query);
+
+                        "glDeleteQueries"
+                    }
+                    GlObject::Sync(sync) => {
+                        self.gl.DeleteSync(sync);
+
+                        "glDeleteSync"
+                    }
+                    GlObject::Texture(texture) => {
+                        self.forget_texture(texture);
+                        self.gl.DeleteTextures(1, &texture);
+
+                        "glDeleteTextures"
+                    }
+                    GlObject::VertexArray(vertex_array) => {
+                        self.forget_vertex_array(vertex_array);
+                        self.gl.DeleteVertexArrays(1, &vertex_array);
+
+                        "glDeleteVertexArrays"
+                    }
+                }
+            };
+
+            result = result.and(self.check(operation));
+        }
+
+        result
+    }
+
+    fn count_state_change(&self) {
+        let mut frame = self.frame.get();
+
+        frame.state_changes += 1;
+        self.frame.set(frame);
+    }
+
+    // Once the context is lost the object goes with it.
+    pub(crate) fn delete_later(&self, object: GlObject) {
+        if let Some((kind, id)) = object.resource() {
+            self.registry.borrow_mut().remove(kind, id);
+        }
+
+        if !self.lost.get() {
+            self.deleted.borrow_mut().push(object);
+        }
+    }
+
+    // Disables the arrays of the bound vertex array that a draw doesn't use,
+    // leaving the others enabled for the next draw.
+    #[track_caller]
+    pub(crate) fn disable_vertex_attribs(
+        &self,
+        used: impl Fn(c_uint) -> bool,
+    ) -> Result<(), GlError> {
+        let mut state = self.state.borrow_mut();
+
+        let Some(vertex_array) = state.current_vertex_array() else {
+            return Ok(());
+        };
+
+        let unused = vertex_array
+            .attributes
+            .keys()
+            .copied()
+            .filter(|location| !used(*location))
+            .collect::<Vec<_>>();
+
+        for location in unused {
+            vertex_array.attributes.remove(&location);
+            self.count_state_change();
+
+            unsafe {
+                self.gl.DisableVertexAttribArray(location);
+            }
+
+            self.check("glDisableVertexAttribArray")?;
+        }
+
+        Ok(())
+    }
+
+    fn forget_buffer(&self, buffer: c_uint) {
+        let mut state = self.state.borrow_mut();
+
+        state.buffers.retain(|_, bound| *bound != buffer);
+
+        for vertex_array in state.vertex_arrays.values_mut() {
+            if vertex_array.element_buffer == Some(buffer) {
+                vertex_array.element_buffer = None;
+            }
+
+            for attribute in vertex_array.attributes.values_mut() {
+                if attribute.buffer == buffer {
+                    attribute.buffer = 0;
+                }
+            }
+        }
+    }
+
+    fn forget_program(&self, program: c_uint) {
+        let mut state = self.state.borrow_mut();
+
+        if state.program == Some(program) {
+            state.program = None;
+        }
+    }
+
+    fn forget_texture(&self, texture: c_uint) {
+        self.state
+            .borrow_mut()
+            .textures
+            .retain(|_, bound| *bound != texture);
+    }
+
+    fn forget_vertex_array(&self, vertex_array: c_uint) {
+        let mut state = self.state.borrow_mut();
+
+        state.vertex_arrays.remove(&vertex_array);
+
+        if state.vertex_array == Some(vertex_array) {
+            state.vertex_array = None;
+        }
+    }
+
+    pub(crate) fn invalidate(&self) {
+        *self.state.borrow_mut() = StateCache::default();
+    }
+
+    pub(crate) fn is_lost(&self) -> bool {
+        self.lost.get()
+    }
+
+    // The last point at which the context is current, reached when the
+    // renderer is dropped or the window is about to destroy the GL context,
+    // whichever comes first. GL must stop calling into the debug callback
+    // before its box is freed. Nothing reaches GL afterwards.
+    pub(crate) fn lose(&self) {
+        if self.lost.replace(true) {
+            return;
+        }
+
+        self.collect_garbage().ok();
+
+        if self.debug_callback.borrow().is_some() {
+            unsafe {
+                self.gl.DebugMessageCallback(None, null());
+            }
+
+            self.check("glDebugMessageCallback").ok();
+        }
+    }
+
+    pub(crate) fn new(gl: GlFns) -> Self {
+        Self {
+            debug_callback: RefCell::default(),
+            deleted: RefCell::default(),
+            frame: Cell::default(),
+            gl,
+            leak_callback: RefCell::default(),
+            lost: Cell::new(false),
+            registry: RefCell::default(),
+            state: RefCell::default(),
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn object_label(
+        &self,
+        kind: ResourceKind,
+        name: c_uint,
+        label: &str,
+    ) -> Result<(), GlError> {
+        self.registry.borrow_mut().set_label(kind, name, label);
+
+        unsafe {
+            self.gl
+                .ObjectLabel(kind.gl_type(), name, label.len() as _, label.as_ptr());
+        }
+
+        self.check("glObjectLabel")
+    }
+
+    pub(crate) fn pipeline(&self) -> PipelineState {
+        self.state.borrow().pipeline.unwrap_or_default()
+    }
+
+    #[track_caller]
+    pub(crate) fn pop_debug_group(&self) -> Result<(), GlError> {
+        unsafe {
+            self.gl.PopDebugGroup();
+        }
+
+        self.check("glPopDebugGroup")
+    }
+
+    #[track_caller]
+    pub(crate) fn push_debug_group(&self, name: &str) -> Result<(), GlError> {
+        unsafe {
+            self.gl.PushDebugGroup(
+                GL_DEBUG_SOURCE_APPLICATION,
+                0,
+                name.len() as _,
+                name.as_ptr(),
+            );
+        }
+
+        self.check("glPushDebugGroup")
+    }
+
+    // Indirect draws pass a count of zero, only the GPU knows their triangles.
+    pub(crate) fn record_draw(&self, primitive: Primitive, count: usize, instances: usize) {
+        let mut frame = self.frame.get();
+
+        frame.draw_calls += 1;
+        frame.triangles += primitive.triangles(count) * instances;
+        self.frame.set(frame);
+    }
+
+    pub(crate) fn register(&self, kind: ResourceKind, id: c_uint, memory: usize) {
+        self.registry.borrow_mut().insert(kind, id, memory);
+    }
+
+    // Runs once, over whatever is still registered.
+    pub(crate) fn report_leaks(&self) {
+        let Some(mut callback) = self.leak_callback.take() else {
+            return;
+        };
+
+        let resources = self.resources();
+
+        for resource in &resources {
+            callback(resource);
+        }
+    }
+
+    pub(crate) fn resource_usage(&self) -> HashMap<ResourceKind, ResourceUsage> {
+        self.registry.borrow().usage()
+    }
+
+    pub(crate) fn resources(&self) -> Vec<Resource> {
+        self.registry.borrow().resources()
+    }
+
+    // The callback is boxed so the pointer handed to GL stays valid until it
+    // is replaced. Synchronous output keeps messages on this thread, next to
+    // the call that caused them. If GL didn't take the new callback, it may
+    // still call the old one, so that one is kept.
+    #[track_caller]
+    pub(crate) fn set_debug_callback(
+        &self,
+        callback: Option<Box<DebugCallback>>,
+    ) -> Result<(), GlError> {
+        unsafe {
+            match &callback {
+                Some(callback) => {
+                    self.gl.DebugMessageCallback(
+                        Some(debug_callback),
+                        (&**callback as *const DebugCallback).cast(),
+                    );
+                }
+                None => self.gl.DebugMessageCallback(None, null()),
+            }
+        }
+
+        self.check("glDebugMessageCallback")?;
+
+        let enabled = callback.is_some();
+
+        *self.debug_callback.borrow_mut() = callback;
+        self.set_enabled(GL_DEBUG_OUTPUT, enabled)?;
+        self.set_enabled(GL_DEBUG_OUTPUT_SYNCHRONOUS, enabled)
+    }
+
+    #[track_caller]
+    pub(crate) fn set_enabled(&self, cap: EnableCap, enabled: bool) -> Result<(), GlError> {
+        if self.state.borrow_mut().enabled.insert(cap, enabled) == Some(enabled) {
+            return Ok(());
+        }
+
+        self.count_state_change();
+
+        unsafe {
+            if enabled {
+                self.gl.Enable(cap);
+            } else {
+                self.gl.Disable(cap);
+            }
+        }
+
+        self.check(if enabled { "glEnable" } else { "glDisable" })
+    }
+
+    pub(crate) fn set_leak_callback(&self, callback: Option<Box<LeakCallback>>) {
+        *self.leak_callback.borrow_mut() = callback;
+    }
+
+    #[track_caller]
+    pub(crate) fn set_patch_vertices(&self, patch_vertices: c_int) -> Result<(), GlError> {
+        if self
+            .state
+            .borrow_mut()
+            .patch_vertices
+            .replace(patch_vertices)
+            == Some(patch_vertices)
+        {
+            return Ok(());
+        }
+
+        self.count_state_change();
+
+        unsafe {
+            self.gl.PatchParameteri(GL_PATCH_VERTICES, patch_vertices);
+        }
+
+        self.check("glPatchParameteri")
+    }
+
+    // A pipeline that fails part way leaves the GL state unknown.
+    #[track_caller]
+    pub(crate) fn set_pipeline(&self, pipeline: &PipelineState) -> Result<(), GlError> {
+        let current = self.state.borrow_mut().pipeline.take();
+
+        if current.as_ref() == Some(pipeline) {
+            self.state.borrow_mut().pipeline = current;
+
+            return Ok(());
+        }
+
+        self.count_state_change();
+        pipeline.apply(self, current.as_ref())?;
+        self.state.borrow_mut().pipeline = Some(*pipeline);
+
+        Ok(())
+    }
+
+    // An array that fails part way is marked as needing to be specified again.
+    #[track_caller]
+    pub(crate) fn set_vertex_attrib(
+        &self,
+        location: c_uint,
+        attrib: VertexAttribState,
+    ) -> Result<(), GlError> {
+        let cached = self
+            .state
+            .borrow_mut()
+            .current_vertex_array()
+            .and_then(|vertex_array| vertex_array.attributes.insert(location, attrib));
+
+        if cached == Some(attrib) {
+            return Ok(());
+        }
+
+        self.count_state_change();
+
+        let result = self.specify_vertex_attrib(location, attrib, cached);
+
+        if result.is_err() {
+            if let Some(vertex_array) = self.state.borrow_mut().current_vertex_array() {
+                vertex_array.attributes.insert(
+                    location,
+                    VertexAttribState {
+                        buffer: 0,
+                        ..attrib
+                    },
+                );
+            }
+        }
+
+        result
+    }
+
+    #[track_caller]
+    fn specify_vertex_attrib(
+        &self,
+        location: c_uint,
+        attrib: VertexAttribState,
+        cached: Option<VertexAttribState>,
+    ) -> Result<(), GlError> {
+        self.bind_buffer(GL_ARRAY_BUFFER, attrib.buffer)?;
+
+        if cached.is_none() {
+            unsafe {
+                self.gl.EnableVertexAttribArray(location);
+            }
+
+            self.check("glEnableVertexAttribArray")?;
+        }
+
+        let operation = unsafe {
+            match attrib.component_type {
+                ComponentType::Float => {
+                    self.gl.VertexAttribPointer(
+                        location,
+                        attrib.components as _,
+                        attrib.gl_type,
+                        GL_FALSE.0 as _,
+                        attrib.stride as _,
+                        attrib.offset as *const _,
+                    );
+
+                    "glVertexAttribPointer"
+                }
+                ComponentType::Int | ComponentType::UnsignedInt => {
+                    self.gl.VertexAttribIPointer(
+                        location,
+                        attrib.components as _,
+                        attrib.gl_type,
+                        attrib.stride as _,
+                        attrib.offset as *const _,
+                    );
+
+                    "glVertexAttribIPointer"
+                }
+                ComponentType::Double => {
+                    self.gl.VertexAttribLPointer(
+                        location,
+                        attrib.components as _,
+                        attrib.gl_type,
+                        attrib.stride as _,
+                        attrib.offset as *const _,
+                    );
+
+                    "glVertexAttribLPointer"
+                }
+            }
+        };
+
+        self.check(operation)?;
+
+        if cached.is_none_or(|cached| cached.divisor != attrib.divisor) {
+            unsafe {
+                self.gl.VertexAttribDivisor(location, attrib.divisor);
+            }
+
+            self.check("glVertexAttribDivisor")?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn take_frame_stats(&self) -> FrameStats {
+        self.frame.take()
+    }
+
+    #[track_caller]
+    pub(crate) fn use_program(&self, program: c_uint) -> Result<(), GlError> {
+        if self.state.borrow_mut().program.replace(program) == Some(program) {
+            return Ok(());
+        }
+
+        self.count_state_change();
+        self.gl.UseProgram(program);
+        self.check("glUseProgram")
+    }
+}
+
+impl Deref for Context {
+    type Target = GlFns;
+
+    fn deref(&self) -> &GlFns {
+        &self.gl
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{context::Context, gl_error::GlError};
+use gl46::{
+    GLenum, GL_DEBUG_SEVERITY_HIGH, GL_DEBUG_SEVERITY_LOW, GL_DEBUG_SEVERITY_MEDIUM,
+    GL_DEBUG_SEVERITY_NOTIFICATION, GL_DEBUG_SOURCE_API, GL_DEBUG_SOURCE_APPLICATION,
+    GL_DEBUG_SOURCE_OTHER, GL_DEBUG_SOURCE_SHADER_COMPILER, GL_DEBUG_SOURCE_THIRD_PARTY,
+    GL_DEBUG_SOURCE_WINDOW_SYSTEM, GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR, GL_DEBUG_TYPE_ERROR,
+    GL_DEBUG_TYPE_MARKER, GL_DEBUG_TYPE_OTHER, GL_DEBUG_TYPE_PERFORMANCE, GL_DEBUG_TYPE_POP_GROUP,
+    GL_DEBUG_TYPE_PORTABILITY, GL_DEBUG_TYPE_PUSH_GROUP, GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR,
+    GL_DONT_CARE,
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_int, c_uint, c_void},
+    fmt,
+    mem::ManuallyDrop,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, strum::Display)]
+pub enum DebugSeverity {
+    #[strum(serialize = "notification")]
+    Notification,
+    #[strum(serialize = "low")]
+    Low,
+    #[strum(serialize = "medium")]
+    Medium,
+    #[strum(serialize = "high")]
+    High,
+}
+
+impl DebugSeverity {
+    fn from_gl(severity: GLenum) -> Self {
+        match severity {
+            GL_DEBUG_SEVERITY_HIGH => Self::High,
+            GL_DEBUG_SEVERITY_MEDIUM => Self::Medium,
+            GL_DEBUG_SEVERITY_LOW => Self::Low,
+            _ => Self::Notification,
+        }
+    }
+
+    fn gl_type(self) -> GLenum {
+        match self {
+            Self::Notification => GL_DEBUG_SEVERITY_NOTIFICATION,
+            Self::Low => GL_DEBUG_SEVERITY_LOW,
+            Self::Medium => GL_DEBUG_SEVERITY_MEDIUM,
+            Self::High => GL_DEBUG_SEVERITY_HIGH,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum::Display)]
+pub enum DebugSource {
+    #[strum(serialize = "api")]
+    Api,
+    #[strum(serialize = "window system")]
+    WindowSystem,
+    #[strum(serialize = "shader compiler")]
+    ShaderCompiler,
+    #[strum(serialize = "third party")]
+    ThirdParty,
+    #[strum(serialize = "application")]
+    Application,
+    #[strum(serialize = "other")]
+    Other,
+}
+
+impl DebugSource {
+    fn from_gl(source: GLenum) -> Self {
+        match source {
+            GL_DEBUG_SOURCE_API => Self::Api,
+            GL_DEBUG_SOURCE_WINDOW_SYSTEM => Self::WindowSystem,
+            GL_DEBUG_SOURCE_SHADER_COMPILER => Self::ShaderCompiler,
+            GL_DEBUG_SOURCE_THIRD_PARTY => Self::ThirdParty,
+            GL_DEBUG_SOURCE_APPLICATION => Self::Application,
+            _ => Self::Other,
+        }
+    }
+
+    fn gl_type(self) -> GLenum {
+        match self {
+            Self::Api => GL_DEBUG_SOURCE_API,
+            Self::WindowSystem => GL_DEBUG_SOURCE_WINDOW_SYSTEM,
+            Self::ShaderCompiler => GL_DEBUG_SOURCE_SHADER_COMPILER,
+            Self::ThirdParty => GL_DEBUG_SOURCE_THIRD_PARTY,
+            Self::Application => GL_DEBUG_SOURCE_APPLICATION,
+            Self::Other => GL_DEBUG_SOURCE_OTHER,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum::Display)]
+pub enum DebugType {
+    #[strum(serialize = "error")]
+    Error,
+    #[strum(serialize = "deprecated behavior")]
+    DeprecatedBehavior,
+    #[strum(serialize = "undefined behavior")]
+    UndefinedBehavior,
+    #[strum(serialize = "portability")]
+    Portability,
+    #[strum(serialize = "performance")]
+    Performance,
+    #[strum(serialize = "marker")]
+    Marker,
+    #[strum(serialize = "push group")]
+    PushGroup,
+    #[strum(serialize = "pop group")]
+    PopGroup,
+    #[strum(serialize = "other")]
+    Other,
+}
+
+impl DebugType {
+    fn from_gl(kind: GLenum) -> Self {
+        match kind {
+            GL_DEBUG_TYPE_ERROR => Self::Error,
+            GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR => Self::DeprecatedBehavior,
+            GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR => Self::UndefinedBehavior,
+            GL_DEBUG_TYPE_PORTABILITY => Self::Portability,
+            GL_DEBUG_TYPE_PERFORMANCE => Self::Performance,
+            GL_DEBUG_TYPE_MARKER => Self::Marker,
+            GL_DEBUG_TYPE_PUSH_GROUP => Self::PushGroup,
+            GL_DEBUG_TYPE_POP_GROUP => Self::PopGroup,
+            _ => Self::Other,
+        }
+    }
+
+    fn gl_type(self) -> GLenum {
+        match self {
+            Self::Error => GL_DEBUG_TYPE_ERROR,
+            Self::DeprecatedBehavior => GL_DEBUG_TYPE_DEPRECATED_BEHAVIOR,
+            Self::UndefinedBehavior => GL_DEBUG_TYPE_UNDEFINED_BEHAVIOR,
+            Self::Portability => GL_DEBUG_TYPE_PORTABILITY,
+            Self::Performance => GL_DEBUG_TYPE_PERFORMANCE,
+            Self::Marker => GL_DEBUG_TYPE_MARKER,
+            Self::PushGroup => GL_DEBUG_TYPE_PUSH_GROUP,
+            Self::PopGroup => GL_DEBUG_TYPE_POP_GROUP,
+            Self::Other => GL_DEBUG_TYPE_OTHER,
+        }
+    }
+}
+
+// Unset fields match every message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DebugFilter {
+    pub kind: Option<DebugType>,
+    pub severity: Option<DebugSeverity>,
+    pub source: Option<DebugSource>,
+}
+
+impl DebugFilter {
+    pub(crate) fn gl_types(&self) -> (GLenum, GLenum, GLenum) {
+        (
+            self.source.map_or(GL_DONT_CARE, DebugSource::gl_type),
+            self.kind.map_or(GL_DONT_CARE, DebugType::gl_type),
+            self.severity.map_or(GL_DONT_CARE, DebugSeverity::gl_type),
+        )
+    }
+
+    pub fn with_kind(mut self, kind: DebugType) -> Self {
+        self.kind = Some(kind);
+
+        self
+    }
+
+    pub fn with_severity(mut self, severity: DebugSeverity) -> Self {
+        self.severity = Some(severity);
+
+        self
+    }
+
+    pub fn with_source(mut self, source: DebugSource) -> Self {
+        self.source = Some(source);
+
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DebugMessage<'a> {
+    pub id: c_uint,
+    pub kind: DebugType,
+    pub message: &'a str,
+    pub severity: DebugSeverity,
+    pub source: DebugSource,
+}
+
+impl fmt::Display for DebugMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "GL {} {} ({}, {}): {}",
+            self.severity, self.kind, self.source, self.id, self.message
+        )
+    }
+}
+
+pub(crate) type DebugCallback = RefCell<Box<dyn FnMut(&DebugMessage)>>;
+
+// Messages are delivered synchronously, so one raised from inside the
+// user callback would re-enter it; those are dropped.
+pub(crate) extern "system" fn debug_callback(
+    source: GLenum,
+    kind: GLenum,
+    id: c_uint,
+    severity: GLenum,
+    length: c_int,
+    message: *const u8,
+    callback: *const c_void,
+) {
+    let callback = unsafe { &*callback.cast::<DebugCallback>() };
+
+    let Ok(mut callback) = callback.try_borrow_mut() else {
+        return;
+    };
+
+    let message = unsafe { std::slice::from_raw_parts(message, length.max(0) as usize) };
+
+    callback(&DebugMessage {
+        id,
+        kind: DebugType::from_gl(kind),
+        message: String::from_utf8_lossy(message)
+            .trim_end_matches('\0')
+            .trim_end(),
+        severity: DebugSeverity::from_gl(severity),
+        source: DebugSource::from_gl(source),
+    });
+}
+
+// Pops its group when dropped, or through `end` to learn whether popping failed.
+pub struct DebugGroup<'a> {
+    gl: &'a Context,
+}
+
+impl<'a> DebugGroup<'a> {
+    #[track_caller]
+    pub fn end(self) -> Result<(), GlError> {
+        let gl = ManuallyDrop::new(self).gl;
+
+        gl.pop_debug_group()
+    }
+
+    #[track_caller]
+    pub(crate) fn new(gl: &'a Context, name: &str) -> Result<Self, GlError> {
+        gl.push_debug_group(name)?;
+
+        Ok(Self { gl })
+    }
+}
+
+impl Drop for DebugGroup<'_> {
+    fn drop(&mut self) {
+        if !self.gl.is_lost() {
+            self.gl.pop_debug_group().ok();
+        }
+    }
+}
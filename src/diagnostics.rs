@@ -0,0 +1,346 @@
+use crate::{preprocessor::PreprocessedSource, program::ShaderStage};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+pub enum Severity {
+    #[strum(serialize = "error")]
+    Error,
+    #[strum(serialize = "warning")]
+    Warning,
+    #[strum(serialize = "note")]
+    Note,
+}
+
+impl Severity {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" | "fatal error" => Some(Self::Error),
+            "warning" => Some(Self::Warning),
+            "note" | "info" => Some(Self::Note),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub column: Option<usize>,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub message: String,
+    pub severity: Severity,
+    pub source_string: Option<usize>,
+    pub stage: Option<ShaderStage>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ShaderLog {
+    pub diagnostics: Vec<Diagnostic>,
+    sources: Vec<(ShaderStage, PreprocessedSource)>,
+}
+
+impl ShaderLog {
+    pub(crate) fn parse(
+        log: &str,
+        stage: Option<ShaderStage>,
+        sources: Vec<(ShaderStage, PreprocessedSource)>,
+    ) -> Self {
+        let mut stage = stage;
+        let mut diagnostics = Vec::new();
+
+        for line in log.trim_end_matches('\0').lines() {
+            let line = line.trim_end();
+
+            if line.is_empty() || line.bytes().all(|b| b == b'-') {
+                continue;
+            }
+
+            if let Some(header_stage) = parse_stage_header(line) {
+                stage = Some(header_stage);
+                continue;
+            }
+
+            let mut diagnostic = parse_mesa(line)
+                .or_else(|| parse_nvidia(line))
+                .or_else(|| parse_amd(line))
+                .or_else(|| parse_plain(line))
+                .unwrap_or_else(|| Diagnostic {
+                    column: None,
+                    file: None,
+                    line: None,
+                    message: line.trim().to_owned(),
+                    severity: Severity::Error,
+                    source_string: None,
+                    stage: None,
+                });
+
+            diagnostic.stage = stage;
+            diagnostic.file = diagnostic.source_string.and_then(|source_string| {
+                sources
+                    .iter()
+                    .find(|(source_stage, _)| Some(*source_stage) == stage)
+                    .and_then(|(_, source)| source.file_name(source_string))
+                    .map(str::to_owned)
+            });
+
+            diagnostics.push(diagnostic);
+        }
+
+        Self {
+            diagnostics,
+            sources,
+        }
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    fn source_line(&self, diagnostic: &Diagnostic) -> Option<&str> {
+        let (_, source) = self
+            .sources
+            .iter()
+            .find(|(stage, _)| Some(*stage) == diagnostic.stage)?;
+
+        source.line(diagnostic.source_string?, diagnostic.line?)
+    }
+}
+
+impl fmt::Display for ShaderLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            if let Some(stage) = diagnostic.stage {
+                write!(f, "{stage} shader: ")?;
+            }
+
+            match (&diagnostic.file, diagnostic.line, diagnostic.column) {
+                (Some(file), Some(line), Some(column)) => write!(f, "{file}:{line}:{column}: ")?,
+                (Some(file), Some(line), None) => write!(f, "{file}:{line}: ")?,
+                (None, Some(line), _) => write!(f, "line {line}: ")?,
+                _ => {}
+            }
+
+            writeln!(f, "{}: {}", diagnostic.severity, diagnostic.message)?;
+
+            if let (Some(src), Some(line)) = (self.source_line(diagnostic), diagnostic.line) {
+                let gutter = line.to_string();
+                let indent = src.len() - src.trim_start().len();
+                let (offset, width) = match diagnostic.column {
+                    Some(column) => (column.saturating_sub(1), 1),
+                    None => (indent, src.trim().len().max(1)),
+                };
+
+                writeln!(f, "{:width$} |", "", width = gutter.len())?;
+                writeln!(f, "{gutter} | {src}")?;
+                writeln!(
+                    f,
+                    "{:gutter$} | {:offset$}{}",
+                    "",
+                    "",
+                    "^".repeat(width),
+                    gutter = gutter.len(),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_stage_header(line: &str) -> Option<ShaderStage> {
+    let header = line
+        .trim()
+        .strip_suffix("info")
+        .or_else(|| line.trim().strip_suffix("shader:"))?
+        .trim()
+        .to_ascii_lowercase();
+
+    match header.as_str() {
+        "vertex" => Some(ShaderStage::Vertex),
+        "tessellation control" | "tess control" => Some(ShaderStage::TessControl),
+        "tessellation evaluation" | "tess evaluation" => Some(ShaderStage::TessEvaluation),
+        "geometry" => Some(ShaderStage::Geometry),
+        "fragment" => Some(ShaderStage::Fragment),
+        _ => None,
+    }
+}
+
+fn parse_number(s: &str) -> Option<(usize, &str)> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+
+    Some((s[..end].parse().ok()?, &s[end..]))
+}
+
+fn location(source_string: usize, line: usize, column: Option<usize>) -> Diagnostic {
+    Diagnostic {
+        column,
+        file: None,
+        line: Some(line),
+        message: String::new(),
+        severity: Severity::Error,
+        source_string: Some(source_string),
+        stage: None,
+    }
+}
+
+// Mesa: `0:12(5): error: message`
+fn parse_mesa(line: &str) -> Option<Diagnostic> {
+    let (source_string, rest) = parse_number(line.trim_start())?;
+    let (line_number, rest) = parse_number(rest.strip_prefix(':')?)?;
+    let (column, rest) = parse_number(rest.strip_prefix('(')?)?;
+    let (severity, message) = rest.strip_prefix("):")?.split_once(':')?;
+
+    Some(Diagnostic {
+        message: message.trim().to_owned(),
+        severity: Severity::parse(severity.trim())?,
+        ..location(source_string, line_number, Some(column))
+    })
+}
+
+// NVIDIA: `0(12) : error C0000: message`
+fn parse_nvidia(line: &str) -> Option<Diagnostic> {
+    let (source_string, rest) = parse_number(line.trim_start())?;
+    let (line_number, rest) = parse_number(rest.strip_prefix('(')?)?;
+    let (kind, message) = rest
+        .strip_prefix(')')?
+        .trim_start()
+        .strip_prefix(':')?
+        .split_once(':')?;
+    let severity = kind.split_whitespace().next()?;
+
+    Some(Diagnostic {
+        message: message.trim().to_owned(),
+        severity: Severity::parse(severity)?,
+        ..location(source_string, line_number, None)
+    })
+}
+
+// AMD: `ERROR: 0:12: message`
+fn parse_amd(line: &str) -> Option<Diagnostic> {
+    let (severity, rest) = line.trim_start().split_once(':')?;
+    let severity = Severity::parse(severity)?;
+    let (source_string, rest) = parse_number(rest.trim_start())?;
+    let (line_number, rest) = parse_number(rest.strip_prefix(':')?)?;
+    let message = rest.strip_prefix(':')?;
+
+    Some(Diagnostic {
+        message: message.trim().to_owned(),
+        severity,
+        ..location(source_string, line_number, None)
+    })
+}
+
+// Location-less messages, e.g. Mesa link errors: `error: message`
+fn parse_plain(line: &str) -> Option<Diagnostic> {
+    let (severity, message) = line.trim_start().split_once(':')?;
+
+    Some(Diagnostic {
+        column: None,
+        file: None,
+        line: None,
+        message: message.trim().to_owned(),
+        severity: Severity::parse(severity)?,
+        source_string: None,
+        stage: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessor::SourceFile;
+
+    fn sources() -> Vec<(ShaderStage, PreprocessedSource)> {
+        vec![(
+            ShaderStage::Fragment,
+            PreprocessedSource {
+                files: vec![
+                    SourceFile {
+                        name: "fragment".to_owned(),
+                        source: "void main() {\n    color = vec4(1.0);\n}".to_owned(),
+                    },
+                    SourceFile {
+                        name: "lighting.glsl".to_owned(),
+                        source: "float light() {\n  return x;\n}".to_owned(),
+                    },
+                ],
+                source: String::new(),
+            },
+        )]
+    }
+
+    #[test]
+    fn parses_mesa() {
+        let log = ShaderLog::parse(
+            "0:2(5): error: `color' undeclared\n\0",
+            Some(ShaderStage::Fragment),
+            sources(),
+        );
+
+        assert_eq!(
+            log.diagnostics,
+            [Diagnostic {
+                column: Some(5),
+                file: Some("fragment".to_owned()),
+                line: Some(2),
+                message: "`color' undeclared".to_owned(),
+                severity: Severity::Error,
+                source_string: Some(0),
+                stage: Some(ShaderStage::Fragment),
+            }]
+        );
+        assert_eq!(
+            log.to_string(),
+            "fragment shader: fragment:2:5: error: `color' undeclared\n  |\n2 |     color = vec4(1.0);\n  |     ^\n"
+        );
+    }
+
+    #[test]
+    fn parses_nvidia() {
+        let log = ShaderLog::parse(
+            "1(2) : warning C7011: implicit cast\n",
+            Some(ShaderStage::Fragment),
+            sources(),
+        );
+
+        let diagnostic = &log.diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.file.as_deref(), Some("lighting.glsl"));
+        assert_eq!(diagnostic.line, Some(2));
+        assert_eq!(diagnostic.column, None);
+        assert_eq!(diagnostic.message, "implicit cast");
+        assert!(log
+            .to_string()
+            .ends_with("2 |   return x;\n  |   ^^^^^^^^^\n"));
+    }
+
+    #[test]
+    fn parses_amd() {
+        let log = ShaderLog::parse(
+            "ERROR: 0:1: 'main' : syntax error\nERROR: 1 compilation errors.",
+            Some(ShaderStage::Fragment),
+            sources(),
+        );
+
+        assert_eq!(log.diagnostics.len(), 2);
+        assert_eq!(log.diagnostics[0].line, Some(1));
+        assert_eq!(log.diagnostics[0].message, "'main' : syntax error");
+        assert_eq!(log.diagnostics[1].line, None);
+        assert_eq!(log.errors().count(), 2);
+    }
+
+    #[test]
+    fn link_log_headers_set_stage() {
+        let log = ShaderLog::parse(
+            "Vertex info\n-----------\n0(3) : error C5145: must write gl_Position\n\nerror: linking failed",
+            None,
+            Vec::new(),
+        );
+
+        assert_eq!(log.diagnostics[0].stage, Some(ShaderStage::Vertex));
+        assert_eq!(log.diagnostics[0].line, Some(3));
+        assert_eq!(log.diagnostics[1].message, "linking failed");
+    }
+}
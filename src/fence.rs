@@ -0,0 +1,81 @@
+use crate::{
+    context::{Context, GlObject},
+    gl_error::GlError,
+};
+use gl46::{
+    GLbitfield, GLsync, GL_ALREADY_SIGNALED, GL_CONDITION_SATISFIED, GL_SIGNALED,
+    GL_SYNC_FLUSH_COMMANDS_BIT, GL_SYNC_GPU_COMMANDS_COMPLETE, GL_SYNC_STATUS, GL_WAIT_FAILED,
+};
+use std::{ptr::null_mut, rc::Rc, time::Duration};
+
+#[derive(Debug, thiserror::Error)]
+pub enum FenceError {
+    #[error(transparent)]
+    Gl(#[from] GlError),
+    #[error("Waiting for the fence failed")]
+    WaitFailed,
+}
+
+pub struct Fence {
+    gl: Rc<Context>,
+    sync: GLsync,
+}
+
+impl Fence {
+    #[track_caller]
+    pub fn is_signaled(&self) -> Result<bool, GlError> {
+        let mut status = 0;
+
+        unsafe {
+            self.gl.GetSynciv(
+                GLsync(self.sync.0),
+                GL_SYNC_STATUS,
+                1,
+                null_mut(),
+                &mut status,
+            );
+        }
+
+        self.gl.check("glGetSynciv")?;
+
+        Ok(status == GL_SIGNALED.0 as _)
+    }
+
+    #[track_caller]
+    pub(crate) fn new(gl: &Rc<Context>) -> Result<Self, GlError> {
+        let fence = Self {
+            gl: gl.clone(),
+            sync: unsafe { gl.FenceSync(GL_SYNC_GPU_COMMANDS_COMPLETE, GLbitfield(0)) },
+        };
+
+        gl.check("glFenceSync")?;
+
+        Ok(fence)
+    }
+
+    // Returns whether the fence was signaled before the timeout ran out. A
+    // failed wait says nothing about the GPU, so it is never reported as done.
+    #[track_caller]
+    pub fn wait(&self, timeout: Duration) -> Result<bool, FenceError> {
+        let timeout = timeout.as_nanos().min(u64::MAX as u128) as u64;
+
+        let status = unsafe {
+            self.gl
+                .ClientWaitSync(GLsync(self.sync.0), GL_SYNC_FLUSH_COMMANDS_BIT, timeout)
+        };
+
+        self.gl.check("glClientWaitSync")?;
+
+        if status == GL_WAIT_FAILED {
+            return Err(FenceError::WaitFailed);
+        }
+
+        Ok(status == GL_ALREADY_SIGNALED || status == GL_CONDITION_SATISFIED)
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        self.gl.delete_later(GlObject::Sync(GLsync(self.sync.0)));
+    }
+}
@@ -0,0 +1,56 @@
+use std::panic::Location;
+
+#[cfg(all(feature = "checked-gl", debug_assertions))]
+use gl46::{
+    GLenum, GL_CONTEXT_LOST, GL_INVALID_ENUM, GL_INVALID_FRAMEBUFFER_OPERATION,
+    GL_INVALID_OPERATION, GL_INVALID_VALUE, GL_OUT_OF_MEMORY, GL_STACK_OVERFLOW,
+    GL_STACK_UNDERFLOW,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum::Display)]
+pub enum GlErrorCode {
+    #[strum(serialize = "invalid enum")]
+    InvalidEnum,
+    #[strum(serialize = "invalid value")]
+    InvalidValue,
+    #[strum(serialize = "invalid operation")]
+    InvalidOperation,
+    #[strum(serialize = "stack overflow")]
+    StackOverflow,
+    #[strum(serialize = "stack underflow")]
+    StackUnderflow,
+    #[strum(serialize = "out of memory")]
+    OutOfMemory,
+    #[strum(serialize = "invalid framebuffer operation")]
+    InvalidFramebufferOperation,
+    #[strum(serialize = "context lost")]
+    ContextLost,
+    #[strum(serialize = "unknown error")]
+    Unknown,
+}
+
+impl GlErrorCode {
+    #[cfg(all(feature = "checked-gl", debug_assertions))]
+    pub(crate) fn from_gl(code: GLenum) -> Self {
+        match code {
+            GL_INVALID_ENUM => Self::InvalidEnum,
+            GL_INVALID_VALUE => Self::InvalidValue,
+            GL_INVALID_OPERATION => Self::InvalidOperation,
+            GL_STACK_OVERFLOW => Self::StackOverflow,
+            GL_STACK_UNDERFLOW => Self::StackUnderflow,
+            GL_OUT_OF_MEMORY => Self::OutOfMemory,
+            GL_INVALID_FRAMEBUFFER_OPERATION => Self::InvalidFramebufferOperation,
+            GL_CONTEXT_LOST => Self::ContextLost,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+// Only produced with the `checked-gl` feature in debug builds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{operation} failed with {code} at {location}")]
+pub struct GlError {
+    pub code: GlErrorCode,
+    pub location: &'static Location<'static>,
+    pub operation: &'static str,
+}
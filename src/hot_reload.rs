@@ -0,0 +1,246 @@
+use crate::{
+    context::Context,
+    preprocessor::{FileSourceProvider, PreprocessedSource},
+    program::{Program, ProgramBuilder, ProgramError, ShaderStage, UniformType},
+    renderer::Renderer,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::c_int,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+pub struct ShaderFiles {
+    defines: Vec<(Box<str>, Box<str>)>,
+    patch_vertices: Option<c_int>,
+    root: PathBuf,
+    stages: Vec<(ShaderStage, Box<str>)>,
+}
+
+impl ShaderFiles {
+    pub fn new(root: impl Into<PathBuf>, vertex_path: &str, fragment_path: &str) -> Self {
+        Self {
+            defines: Vec::new(),
+            patch_vertices: None,
+            root: root.into(),
+            stages: vec![
+                (ShaderStage::Vertex, vertex_path.into()),
+                (ShaderStage::Fragment, fragment_path.into()),
+            ],
+        }
+    }
+
+    pub fn with_define(mut self, name: &str, value: impl ToString) -> Self {
+        self.defines
+            .push((name.into(), value.to_string().into_boxed_str()));
+
+        self
+    }
+
+    pub fn with_geometry(self, geometry_path: &str) -> Self {
+        self.with_stage(ShaderStage::Geometry, geometry_path)
+    }
+
+    pub fn with_patch_vertices(mut self, patch_vertices: c_int) -> Self {
+        self.patch_vertices = Some(patch_vertices);
+
+        self
+    }
+
+    pub fn with_tess_control(self, tess_control_path: &str) -> Self {
+        self.with_stage(ShaderStage::TessControl, tess_control_path)
+    }
+
+    pub fn with_tess_evaluation(self, tess_evaluation_path: &str) -> Self {
+        self.with_stage(ShaderStage::TessEvaluation, tess_evaluation_path)
+    }
+
+    fn with_stage(mut self, stage: ShaderStage, path: &str) -> Self {
+        self.stages.retain(|(existing, _)| *existing != stage);
+        self.stages.push((stage, path.into()));
+
+        self
+    }
+}
+
+type RetainedUniforms = HashMap<Box<str>, Box<dyn UniformType>>;
+
+type WatchedFiles = Vec<(PathBuf, Option<SystemTime>)>;
+
+// Uniforms set through the watched program are kept so they can be replayed
+// on the program that replaces it.
+pub struct WatchedProgram {
+    files: ShaderFiles,
+    gl: Rc<Context>,
+    program: Program,
+    uniforms: RefCell<RetainedUniforms>,
+    watched: WatchedFiles,
+}
+
+impl WatchedProgram {
+    pub fn changed(&self) -> bool {
+        self.watched
+            .iter()
+            .any(|(path, modified)| modified_time(path) != *modified)
+    }
+
+    #[track_caller]
+    pub(crate) fn new(renderer: &Renderer, files: ShaderFiles) -> Result<Self, ProgramError> {
+        let gl = renderer.gl.clone();
+        let (program, watched) = Self::compile(&gl, &files)?;
+
+        Ok(Self {
+            files,
+            gl,
+            program,
+            uniforms: RefCell::default(),
+            watched,
+        })
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    #[track_caller]
+    pub fn reload(&mut self) -> Result<bool, ProgramError> {
+        if !self.changed() {
+            return Ok(false);
+        }
+
+        for (path, modified) in &mut self.watched {
+            *modified = modified_time(path);
+        }
+
+        let (program, watched) = Self::compile(&self.gl, &self.files)?;
+
+        // Uniforms the new program no longer has are kept for later reloads.
+        for (name, value) in self.uniforms.get_mut().iter() {
+            match program.set_uniform(name, &**value) {
+                Ok(()) | Err(ProgramError::NoSuchUniform(_)) => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        self.program = program;
+        self.watched = watched;
+
+        Ok(true)
+    }
+
+    #[track_caller]
+    pub fn set_uniform(
+        &self,
+        name: &str,
+        value: &(impl UniformType + Clone + 'static),
+    ) -> Result<(), ProgramError> {
+        self.uniforms
+            .borrow_mut()
+            .insert(name.into(), Box::new(value.clone()));
+
+        self.program.set_uniform(name, value)
+    }
+
+    #[track_caller]
+    fn compile(
+        gl: &Rc<Context>,
+        files: &ShaderFiles,
+    ) -> Result<(Program, WatchedFiles), ProgramError> {
+        let provider = FileSourceProvider::new(&files.root);
+
+        let sources = files
+            .stages
+            .iter()
+            .map(|(stage, path)| {
+                let path = provider.path(path);
+
+                fs::read_to_string(&path)
+                    .map(|src| (*stage, src))
+                    .map_err(|error| ProgramError::SourceRead(path, error))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let src = |stage| {
+            sources
+                .iter()
+                .find(|(source_stage, _)| *source_stage == stage)
+                .map(|(_, src)| src.as_str())
+        };
+
+        let mut builder = ProgramBuilder::from_context(
+            gl.clone(),
+            src(ShaderStage::Vertex).unwrap_or_default(),
+            src(ShaderStage::Fragment).unwrap_or_default(),
+        )
+        .with_source_provider(&provider);
+
+        if let Some(src) = src(ShaderStage::TessControl) {
+            builder = builder.with_tess_control(src);
+        }
+
+        if let Some(src) = src(ShaderStage::TessEvaluation) {
+            builder = builder.with_tess_evaluation(src);
+        }
+
+        if let Some(src) = src(ShaderStage::Geometry) {
+            builder = builder.with_geometry(src);
+        }
+
+        for (stage, path) in &files.stages {
+            builder = builder.with_source_name(*stage, path);
+        }
+
+        if let Some(patch_vertices) = files.patch_vertices {
+            builder = builder.with_patch_vertices(patch_vertices);
+        }
+
+        for (name, value) in &files.defines {
+            builder = builder.with_define(name, value);
+        }
+
+        let (program, preprocessed) = builder.build_with_sources()?;
+
+        Ok((
+            program,
+            watched_files(&provider, &files.stages, &preprocessed),
+        ))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn watched_files(
+    provider: &FileSourceProvider,
+    stages: &[(ShaderStage, Box<str>)],
+    preprocessed: &[(ShaderStage, PreprocessedSource)],
+) -> WatchedFiles {
+    let mut paths = stages
+        .iter()
+        .map(|(_, path)| provider.path(path))
+        .collect::<Vec<_>>();
+
+    for (_, source) in preprocessed {
+        for file in source.files.iter().skip(1) {
+            let path = provider.path(&file.name);
+
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let modified = modified_time(&path);
+
+            (path, modified)
+        })
+        .collect()
+}
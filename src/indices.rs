@@ -1,55 +1,50 @@
+use crate::{
+    buffer::{Buffer, BufferError, BufferObject, BufferStorage},
+    context::Context,
+    gl_error::GlError,
+};
 use gl46::{
-    DrawElementsType, GlFns, GL_ELEMENT_ARRAY_BUFFER, GL_STATIC_DRAW, GL_UNSIGNED_BYTE,
-    GL_UNSIGNED_INT, GL_UNSIGNED_SHORT,
+    DrawElementsType, GL_ELEMENT_ARRAY_BUFFER, GL_UNSIGNED_BYTE, GL_UNSIGNED_INT, GL_UNSIGNED_SHORT,
 };
-use std::{ffi::c_uint, marker::PhantomData};
+use std::rc::Rc;
 
-pub struct Indices<'a, I: IndexType, const SIZE: usize> {
-    buffer: c_uint,
-    gl: &'a GlFns,
-    len: usize,
-    _p: PhantomData<I>,
+pub struct Indices<I: IndexType, const SIZE: usize> {
+    buffer: Buffer<I>,
 }
 
-impl<'a, I: IndexType, const SIZE: usize> Indices<'a, I, SIZE> {
-    pub(crate) fn bind(&self) {
-        unsafe {
-            self.gl.BindBuffer(GL_ELEMENT_ARRAY_BUFFER, self.buffer);
-        }
+impl<I: IndexType, const SIZE: usize> Indices<I, SIZE> {
+    #[track_caller]
+    pub(crate) fn bind(&self) -> Result<(), GlError> {
+        self.buffer.bind(GL_ELEMENT_ARRAY_BUFFER)
     }
 
-    pub(crate) fn len(&self) -> usize {
-        self.len
+    pub(crate) fn buffer(&self) -> Rc<BufferObject> {
+        self.buffer.object()
     }
 
-    pub(crate) fn new(gl: &'a GlFns, indices: &[I; SIZE]) -> Self {
-        let mut buffer = 0;
+    pub(crate) fn len(&self) -> usize {
+        self.buffer.len()
+    }
 
-        unsafe {
-            gl.GenBuffers(1, &mut buffer);
-            gl.BindBuffer(GL_ELEMENT_ARRAY_BUFFER, buffer);
-            gl.BufferData(
-                GL_ELEMENT_ARRAY_BUFFER,
-                (std::mem::size_of::<I>() * indices.len()) as _,
-                indices.as_ptr() as _,
-                GL_STATIC_DRAW,
-            )
-        }
+    #[track_caller]
+    pub(crate) fn new(
+        gl: &Rc<Context>,
+        indices: &[I; SIZE],
+        storage: BufferStorage,
+    ) -> Result<Self, GlError> {
+        Ok(Self {
+            buffer: Buffer::new(gl, indices, storage)?,
+        })
+    }
 
-        Self {
-            buffer,
-            gl,
-            len: indices.len(),
-            _p: PhantomData,
-        }
+    #[track_caller]
+    pub fn set_label(&self, label: &str) -> Result<(), GlError> {
+        self.buffer.set_label(label)
     }
-}
 
-impl<I: IndexType, const SIZE: usize> Drop for Indices<'_, I, SIZE> {
-    fn drop(&mut self) {
-        unsafe {
-            self.gl.DeleteBuffers(1, &self.buffer);
-        }
+    #[track_caller]
+    pub fn update(&self, offset: usize, indices: &[I]) -> Result<(), BufferError> {
+        self.buffer.update(offset, indices)
     }
 }
 
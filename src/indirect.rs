@@ -0,0 +1,93 @@
+use crate::{
+    buffer::{Buffer, BufferError, BufferStorage},
+    context::Context,
+    gl_error::GlError,
+};
+use gl46::{GL_DRAW_INDIRECT_BUFFER, GL_PARAMETER_BUFFER};
+use std::rc::Rc;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawArraysIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first: u32,
+    pub base_instance: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawElementsIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub base_instance: u32,
+}
+
+pub struct DrawIndirectBuffer<C: IndirectCommand> {
+    buffer: Buffer<C>,
+}
+
+impl<C: IndirectCommand> DrawIndirectBuffer<C> {
+    #[track_caller]
+    pub(crate) fn bind(&self) -> Result<(), GlError> {
+        self.buffer.bind(GL_DRAW_INDIRECT_BUFFER)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.len() == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    #[track_caller]
+    pub(crate) fn new(gl: &Rc<Context>, commands: &[C]) -> Result<Self, GlError> {
+        Ok(Self {
+            buffer: Buffer::new(gl, commands, BufferStorage::Dynamic)?,
+        })
+    }
+
+    #[track_caller]
+    pub fn update(&self, offset: usize, commands: &[C]) -> Result<(), BufferError> {
+        self.buffer.update(offset, commands)
+    }
+}
+
+pub struct DrawCountBuffer {
+    buffer: Buffer<u32>,
+}
+
+impl DrawCountBuffer {
+    #[track_caller]
+    pub(crate) fn bind(&self) -> Result<(), GlError> {
+        self.buffer.bind(GL_PARAMETER_BUFFER)
+    }
+
+    #[track_caller]
+    pub(crate) fn new(gl: &Rc<Context>, count: u32) -> Result<Self, GlError> {
+        Ok(Self {
+            buffer: Buffer::new(gl, &[count], BufferStorage::Dynamic)?,
+        })
+    }
+
+    #[track_caller]
+    pub fn set(&self, count: u32) -> Result<(), BufferError> {
+        self.buffer.update(0, &[count])
+    }
+}
+
+pub trait IndirectCommand: sealed::Sealed + Copy {}
+
+impl IndirectCommand for DrawArraysIndirectCommand {}
+
+impl IndirectCommand for DrawElementsIndirectCommand {}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::DrawArraysIndirectCommand {}
+    impl Sealed for super::DrawElementsIndirectCommand {}
+}
@@ -1,17 +1,127 @@
+mod buffer;
+mod clear;
+mod color;
+mod command_buffer;
+mod context;
+mod debug;
+mod diagnostics;
+mod fence;
+mod gl_error;
+mod hot_reload;
 mod indices;
+mod indirect;
+mod mesh;
+#[cfg(test)]
+mod mock_gl;
+mod pipeline;
+mod preprocessor;
+mod primitive;
+mod profiler;
 mod program;
+mod program_cache;
+mod query;
+mod reflection;
 mod renderer;
+mod ring_buffer;
+mod spirv;
+mod stats;
 mod vertices;
+mod viewport;
 mod window;
 
+pub use buffer::BufferError;
+pub use clear::ClearBuffers;
+pub use clear::ClearColor;
+pub use color::linear_to_srgb;
+pub use color::srgb_to_linear;
+pub use color::Color;
+pub use color::ColorError;
+pub use command_buffer::Command;
+pub use command_buffer::CommandBuffer;
+pub use command_buffer::MeshHandle;
+pub use command_buffer::ProgramHandle;
+pub use command_buffer::SubmitError;
+pub use command_buffer::UniformValue;
+pub use debug::DebugFilter;
+pub use debug::DebugGroup;
+pub use debug::DebugMessage;
+pub use debug::DebugSeverity;
+pub use debug::DebugSource;
+pub use debug::DebugType;
+pub use diagnostics::Diagnostic;
+pub use diagnostics::Severity;
+pub use diagnostics::ShaderLog;
+pub use fence::Fence;
+pub use fence::FenceError;
+pub use gl_error::GlError;
+pub use gl_error::GlErrorCode;
 pub use glam as math;
+pub use hot_reload::ShaderFiles;
+pub use hot_reload::WatchedProgram;
 pub use indices::IndexType;
 pub use indices::Indices;
+pub use indirect::DrawArraysIndirectCommand;
+pub use indirect::DrawCountBuffer;
+pub use indirect::DrawElementsIndirectCommand;
+pub use indirect::DrawIndirectBuffer;
+pub use indirect::IndirectCommand;
+pub use mesh::Mesh;
+pub use pipeline::Blend;
+pub use pipeline::BlendComponent;
+pub use pipeline::BlendFactor;
+pub use pipeline::BlendOp;
+pub use pipeline::ColorMask;
+pub use pipeline::CompareFunction;
+pub use pipeline::CullMode;
+pub use pipeline::Depth;
+pub use pipeline::FrontFace;
+pub use pipeline::PipelineState;
+pub use pipeline::PolygonMode;
+pub use pipeline::PolygonOffset;
+pub use pipeline::Stencil;
+pub use pipeline::StencilFace;
+pub use pipeline::StencilOp;
+pub use preprocessor::FileSourceProvider;
+pub use preprocessor::MemorySourceProvider;
+pub use preprocessor::PreprocessError;
+pub use preprocessor::PreprocessedSource;
+pub use preprocessor::Preprocessor;
+pub use preprocessor::SourceFile;
+pub use preprocessor::SourceProvider;
+pub use primitive::Primitive;
+pub use profiler::FrameReport;
+pub use profiler::PassTiming;
+pub use profiler::Profiler;
+pub use profiler::ProfilerScope;
 pub use program::Program;
 pub use program::ProgramBuilder;
+pub use program::ShaderStage;
+pub use program_cache::ProgramCache;
+pub use query::Query;
+pub use query::QueryKind;
+pub use reflection::AttributeInfo;
+pub use reflection::ComponentType;
+pub use reflection::VertexLayoutError;
+pub use renderer::DrawError;
+pub use renderer::MeshStage;
 pub use renderer::Renderer;
+pub use ring_buffer::RingBuffer;
+pub use ring_buffer::RingBufferError;
+pub use ring_buffer::RingRange;
+pub use spirv::SpirvError;
+pub use spirv::SpirvShader;
+pub use stats::FrameStats;
+pub use stats::RendererStats;
+pub use stats::Resource;
+pub use stats::ResourceKind;
+pub use stats::ResourceUsage;
 pub use vertices::VertexAttribute;
 pub use vertices::Vertices;
+pub use viewport::Corner;
+pub use viewport::Rect;
+pub use viewport::Viewport;
+pub use viewport::ViewportLayout;
 pub use window::Event;
 pub use window::Key;
 pub use window::Window;
+pub use window::WindowBuilder;
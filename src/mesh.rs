@@ -0,0 +1,275 @@
+use crate::{
+    buffer::BufferObject,
+    command_buffer::{next_generation, MeshHandle},
+    context::{Context, GlObject},
+    gl_error::GlError,
+    indices::IndexType,
+    reflection::{AttributeInfo, ComponentType, VertexLayoutError},
+    stats::ResourceKind,
+    vertices::VertexAttributeType,
+    Indices, Vertices,
+};
+use gl46::{DrawElementsType, GL_FALSE};
+use std::{ffi::c_uint, rc::Rc};
+
+pub(crate) struct MeshAttribute {
+    pub(crate) component_type: ComponentType,
+    pub(crate) components: usize,
+    pub(crate) divisor: c_uint,
+    pub(crate) len: usize,
+    pub(crate) location: c_uint,
+    _buffer: Rc<BufferObject>,
+}
+
+pub(crate) struct MeshIndices {
+    pub(crate) gl_type: DrawElementsType,
+    pub(crate) len: usize,
+    pub(crate) size: usize,
+    _buffer: Rc<BufferObject>,
+}
+
+// Each attribute location gets the vertex buffer binding point of the same
+// index, so rebinding a location replaces its buffer. The mesh holds on to
+// the buffers it reads from until they are replaced or it is dropped.
+pub struct Mesh {
+    attributes: Vec<MeshAttribute>,
+    generation: u64,
+    gl: Rc<Context>,
+    indices: Option<MeshIndices>,
+    vertex_array: c_uint,
+}
+
+impl Mesh {
+    pub(crate) fn attributes(&self) -> &[MeshAttribute] {
+        &self.attributes
+    }
+
+    #[track_caller]
+    pub(crate) fn bind(&self) -> Result<(), GlError> {
+        self.gl.bind_vertex_array(self.vertex_array)
+    }
+
+    pub fn handle(&self) -> MeshHandle {
+        MeshHandle {
+            generation: self.generation,
+            id: self.vertex_array,
+        }
+    }
+
+    pub(crate) fn indices(&self) -> Option<&MeshIndices> {
+        self.indices.as_ref()
+    }
+
+    #[track_caller]
+    pub(crate) fn new(gl: &Rc<Context>) -> Result<Self, GlError> {
+        let mut vertex_array = 0;
+
+        unsafe {
+            gl.CreateVertexArrays(1, &mut vertex_array);
+        }
+
+        gl.check("glCreateVertexArrays")?;
+        gl.register(ResourceKind::VertexArray, vertex_array, 0);
+
+        Ok(Self {
+            attributes: Vec::new(),
+            generation: next_generation(),
+            gl: gl.clone(),
+            indices: None,
+            vertex_array,
+        })
+    }
+
+    #[track_caller]
+    pub fn set_label(&self, label: &str) -> Result<(), GlError> {
+        self.gl
+            .object_label(ResourceKind::VertexArray, self.vertex_array, label)
+    }
+
+    pub(crate) fn validate(&self, attributes: &[AttributeInfo]) -> Result<(), VertexLayoutError> {
+        for attribute in attributes {
+            let columns = attribute.location..attribute.location + attribute.columns as c_uint;
+
+            for location in columns {
+                let Some(bound) = self
+                    .attributes
+                    .iter()
+                    .find(|bound| bound.location == location)
+                else {
+                    return Err(VertexLayoutError::MissingBuffer(attribute.name.clone()));
+                };
+
+                attribute.validate(bound.component_type, bound.components)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn with_indices<I: IndexType, const SIZE: usize>(
+        mut self,
+        indices: &Indices<I, SIZE>,
+    ) -> Result<Self, GlError> {
+        let buffer = indices.buffer();
+
+        unsafe {
+            self.gl
+                .VertexArrayElementBuffer(self.vertex_array, buffer.id());
+        }
+
+        self.gl.check("glVertexArrayElementBuffer")?;
+        self.indices = Some(MeshIndices {
+            gl_type: I::GL_TYPE,
+            len: indices.len(),
+            size: std::mem::size_of::<I>(),
+            _buffer: buffer,
+        });
+
+        Ok(self)
+    }
+
+    #[track_caller]
+    pub fn with_instance_vertices<
+        T: VertexAttributeType,
+        const AMOUNT: usize,
+        const SIZE: usize,
+    >(
+        self,
+        location: c_uint,
+        vertices: &Vertices<T, AMOUNT, SIZE>,
+        divisor: c_uint,
+    ) -> Result<Self, GlError> {
+        self.with_binding(location, vertices, divisor.max(1))
+    }
+
+    #[track_caller]
+    pub fn with_vertices<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize>(
+        self,
+        location: c_uint,
+        vertices: &Vertices<T, AMOUNT, SIZE>,
+    ) -> Result<Self, GlError> {
+        self.with_binding(location, vertices, 0)
+    }
+
+    #[track_caller]
+    fn with_binding<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize>(
+        mut self,
+        location: c_uint,
+        vertices: &Vertices<T, AMOUNT, SIZE>,
+        divisor: c_uint,
+    ) -> Result<Self, GlError> {
+        let buffer = vertices.buffer();
+        let vao = self.vertex_array;
+
+        unsafe {
+            self.gl.VertexArrayVertexBuffer(
+                vao,
+                location,
+                buffer.id(),
+                0,
+                std::mem::size_of::<[T; SIZE]>() as _,
+            );
+        }
+
+        self.gl.check("glVertexArrayVertexBuffer")?;
+
+        let operation = unsafe {
+            match T::COMPONENT_TYPE {
+                ComponentType::Float => {
+                    self.gl.VertexArrayAttribFormat(
+                        vao,
+                        location,
+                        SIZE as _,
+                        T::GL_TYPE,
+                        GL_FALSE.0 as _,
+                        0,
+                    );
+
+                    "glVertexArrayAttribFormat"
+                }
+                ComponentType::Int | ComponentType::UnsignedInt => {
+                    self.gl
+                        .VertexArrayAttribIFormat(vao, location, SIZE as _, T::GL_TYPE, 0);
+
+                    "glVertexArrayAttribIFormat"
+                }
+                ComponentType::Double => {
+                    self.gl
+                        .VertexArrayAttribLFormat(vao, location, SIZE as _, T::GL_TYPE, 0);
+
+                    "glVertexArrayAttribLFormat"
+                }
+            }
+        };
+
+        self.gl.check(operation)?;
+
+        unsafe {
+            self.gl.VertexArrayAttribBinding(vao, location, location);
+        }
+
+        self.gl.check("glVertexArrayAttribBinding")?;
+
+        unsafe {
+            self.gl.VertexArrayBindingDivisor(vao, location, divisor);
+        }
+
+        self.gl.check("glVertexArrayBindingDivisor")?;
+
+        unsafe {
+            self.gl.EnableVertexArrayAttrib(vao, location);
+        }
+
+        self.gl.check("glEnableVertexArrayAttrib")?;
+        self.attributes
+            .retain(|attribute| attribute.location != location);
+        self.attributes.push(MeshAttribute {
+            component_type: T::COMPONENT_TYPE,
+            components: SIZE,
+            divisor,
+            len: AMOUNT,
+            location,
+            _buffer: buffer,
+        });
+
+        Ok(self)
+    }
+}
+
+impl Drop for Mesh {
+    fn drop(&mut self) {
+        self.gl
+            .delete_later(GlObject::VertexArray(self.vertex_array));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mock_gl, ResourceKind, VertexAttribute};
+
+    #[test]
+    fn keeps_its_buffers_alive() {
+        let renderer = mock_gl::renderer();
+        let vertices = renderer
+            .create_vertices(&[VertexAttribute::new([0.0; 3])])
+            .unwrap();
+        let mesh = renderer
+            .create_mesh()
+            .unwrap()
+            .with_vertices(0, &vertices)
+            .unwrap();
+
+        drop(vertices);
+        renderer.collect_garbage().unwrap();
+
+        assert!(mock_gl::calls("glDeleteBuffers").is_empty());
+        assert_eq!(renderer.stats().resource(ResourceKind::Buffer).count, 1);
+
+        drop(mesh);
+        renderer.collect_garbage().unwrap();
+
+        assert_eq!(mock_gl::calls("glDeleteBuffers").len(), 1);
+        assert_eq!(renderer.stats().resource(ResourceKind::Buffer).count, 0);
+    }
+}
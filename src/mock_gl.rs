@@ -0,0 +1,301 @@
+// Stands in for a GL context in tests. Every call is recorded with its
+// arguments, names are handed out in order and errors are raised on demand.
+// Functions no test reaches abort when called.
+#![allow(non_snake_case)]
+
+use crate::Renderer;
+use gl46::{
+    GLbitfield, GLenum, GlFns, GL_ACTIVE_ATTRIBUTES, GL_COMPILE_STATUS, GL_INVALID_OPERATION,
+    GL_LINK_STATUS, GL_NO_ERROR,
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, c_uint, c_void, CStr},
+};
+
+#[derive(Default)]
+struct State {
+    attributes: Vec<(&'static str, GLenum, c_int)>,
+    calls: Vec<(&'static str, Vec<i64>)>,
+    errors: Vec<GLenum>,
+    failing: Option<&'static str>,
+    next_name: c_uint,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::default();
+}
+
+trait Arg {
+    fn value(&self) -> i64;
+}
+
+macro_rules! args {
+    ($($ty:ty),*) => {
+        $(
+            impl Arg for $ty {
+                fn value(&self) -> i64 {
+                    *self as i64
+                }
+            }
+        )*
+    };
+}
+
+args!(c_int, c_uint, f32, f64, isize, u8);
+
+impl Arg for GLenum {
+    fn value(&self) -> i64 {
+        self.0 as i64
+    }
+}
+
+impl Arg for GLbitfield {
+    fn value(&self) -> i64 {
+        self.0 as i64
+    }
+}
+
+impl<T> Arg for *const T {
+    fn value(&self) -> i64 {
+        *self as i64
+    }
+}
+
+impl<T> Arg for *mut T {
+    fn value(&self) -> i64 {
+        *self as i64
+    }
+}
+
+macro_rules! stubs {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            extern "system" fn $name($($arg: $ty),*) {
+                record(stringify!($name), vec![$($arg.value()),*]);
+            }
+        )*
+
+        fn stub(name: &str) -> Option<*const c_void> {
+            match name {
+                $(stringify!($name) => Some($name as *const c_void),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+stubs! {
+    glAttachShader(program: c_uint, shader: c_uint);
+    glBindBuffer(target: GLenum, buffer: c_uint);
+    glBindVertexArray(array: c_uint);
+    glBlendColor(red: f32, green: f32, blue: f32, alpha: f32);
+    glBlendEquationSeparatei(buffer: c_uint, color: GLenum, alpha: GLenum);
+    glBlendFuncSeparatei(buffer: c_uint, src: GLenum, dst: GLenum, src_alpha: GLenum, dst_alpha: GLenum);
+    glColorMaski(index: c_uint, red: u8, green: u8, blue: u8, alpha: u8);
+    glCompileShader(shader: c_uint);
+    glCullFace(mode: GLenum);
+    glDeleteBuffers(n: c_int, buffers: *const c_uint);
+    glDeleteProgram(program: c_uint);
+    glDeleteQueries(n: c_int, queries: *const c_uint);
+    glDeleteShader(shader: c_uint);
+    glDeleteVertexArrays(n: c_int, arrays: *const c_uint);
+    glDepthFunc(func: GLenum);
+    glDepthMask(flag: u8);
+    glDepthRange(near: f64, far: f64);
+    glDetachShader(program: c_uint, shader: c_uint);
+    glDisable(cap: GLenum);
+    glDisableVertexAttribArray(index: c_uint);
+    glDisablei(cap: GLenum, index: c_uint);
+    glDrawArraysInstancedBaseInstance(mode: GLenum, first: c_int, count: c_int, instances: c_int, base_instance: c_uint);
+    glEnable(cap: GLenum);
+    glEnableVertexArrayAttrib(array: c_uint, index: c_uint);
+    glEnableVertexAttribArray(index: c_uint);
+    glEnablei(cap: GLenum, index: c_uint);
+    glFrontFace(mode: GLenum);
+    glLinkProgram(program: c_uint);
+    glNamedBufferStorage(buffer: c_uint, size: isize, data: *const c_void, flags: GLbitfield);
+    glObjectLabel(identifier: GLenum, name: c_uint, length: c_int, label: *const c_char);
+    glPolygonMode(face: GLenum, mode: GLenum);
+    glShaderSource(shader: c_uint, count: c_int, strings: *const *const c_char, lengths: *const c_int);
+    glStencilFuncSeparate(face: GLenum, func: GLenum, reference: c_int, mask: c_uint);
+    glStencilMaskSeparate(face: GLenum, mask: c_uint);
+    glStencilOpSeparate(face: GLenum, fail: GLenum, depth_fail: GLenum, pass: GLenum);
+    glUseProgram(program: c_uint);
+    glVertexArrayAttribBinding(array: c_uint, index: c_uint, binding: c_uint);
+    glVertexArrayAttribFormat(array: c_uint, index: c_uint, size: c_int, kind: GLenum, normalized: u8, offset: c_uint);
+    glVertexArrayBindingDivisor(array: c_uint, binding: c_uint, divisor: c_uint);
+    glVertexArrayVertexBuffer(array: c_uint, binding: c_uint, buffer: c_uint, offset: isize, stride: c_int);
+    glVertexAttribDivisor(index: c_uint, divisor: c_uint);
+    glVertexAttribPointer(index: c_uint, size: c_int, kind: GLenum, normalized: u8, stride: c_int, pointer: *const c_void);
+    glViewport(x: c_int, y: c_int, width: c_int, height: c_int);
+}
+
+extern "system" fn glCreateBuffers(n: c_int, buffers: *mut c_uint) {
+    create_names(n, buffers);
+}
+
+extern "system" fn glCreateProgram() -> c_uint {
+    let mut program = 0;
+
+    create_names(1, &mut program);
+
+    program
+}
+
+extern "system" fn glCreateShader(_kind: GLenum) -> c_uint {
+    let mut shader = 0;
+
+    create_names(1, &mut shader);
+
+    shader
+}
+
+extern "system" fn glCreateVertexArrays(n: c_int, arrays: *mut c_uint) {
+    create_names(n, arrays);
+}
+
+extern "system" fn glGetActiveAttrib(
+    _program: c_uint,
+    index: c_uint,
+    buffer_size: c_int,
+    length: *mut c_int,
+    size: *mut c_int,
+    kind: *mut GLenum,
+    name: *mut u8,
+) {
+    let (attribute, attribute_kind, _) =
+        STATE.with_borrow(|state| state.attributes[index as usize]);
+    let len = attribute.len().min(buffer_size as usize - 1);
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(attribute.as_ptr(), name, len);
+        *name.add(len) = 0;
+        *length = len as _;
+        *size = 1;
+        *kind = attribute_kind;
+    }
+}
+
+extern "system" fn glGetAttribLocation(_program: c_uint, name: *const c_char) -> c_int {
+    let name = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+
+    STATE.with_borrow(|state| {
+        state
+            .attributes
+            .iter()
+            .find(|(attribute, ..)| *attribute == name)
+            .map_or(-1, |(.., location)| *location)
+    })
+}
+
+extern "system" fn glGetError() -> GLenum {
+    STATE.with_borrow_mut(|state| {
+        if state.errors.is_empty() {
+            GL_NO_ERROR
+        } else {
+            state.errors.remove(0)
+        }
+    })
+}
+
+extern "system" fn glGetProgramiv(_program: c_uint, name: GLenum, value: *mut c_int) {
+    let attributes = STATE.with_borrow(|state| state.attributes.len());
+
+    unsafe {
+        *value = match name {
+            GL_ACTIVE_ATTRIBUTES => attributes as _,
+            GL_LINK_STATUS => 1,
+            _ => 0,
+        };
+    }
+}
+
+extern "system" fn glGetShaderiv(_shader: c_uint, name: GLenum, value: *mut c_int) {
+    unsafe {
+        *value = (name == GL_COMPILE_STATUS) as _;
+    }
+}
+
+extern "system" fn unimplemented() {
+    eprintln!("called a GL function the mock doesn't implement");
+    std::process::abort();
+}
+
+// Every call to `name` so far, with its arguments.
+pub(crate) fn calls(name: &str) -> Vec<Vec<i64>> {
+    STATE.with_borrow(|state| {
+        state
+            .calls
+            .iter()
+            .filter(|(call, _)| *call == name)
+            .map(|(_, args)| args.clone())
+            .collect()
+    })
+}
+
+// The next call to `name` raises GL_INVALID_OPERATION.
+#[cfg(all(feature = "checked-gl", debug_assertions))]
+pub(crate) fn fail(name: &'static str) {
+    STATE.with_borrow_mut(|state| state.failing = Some(name));
+}
+
+// Starts from a fresh context, so every test sees only its own calls.
+pub(crate) fn renderer() -> Renderer {
+    STATE.take();
+
+    let gl = unsafe {
+        GlFns::load_from(&|name| {
+            let name = CStr::from_ptr(name.cast()).to_str().unwrap();
+
+            lookup(name).unwrap_or(unimplemented as *const c_void)
+        })
+    };
+
+    Renderer::new(gl.unwrap()).unwrap()
+}
+
+// Active attributes of the programs linked from now on, with their locations.
+pub(crate) fn set_attributes(attributes: &[(&'static str, GLenum, c_int)]) {
+    STATE.with_borrow_mut(|state| state.attributes = attributes.to_vec());
+}
+
+fn create_names(n: c_int, names: *mut c_uint) {
+    STATE.with_borrow_mut(|state| {
+        for i in 0..n as usize {
+            state.next_name += 1;
+
+            unsafe {
+                *names.add(i) = state.next_name;
+            }
+        }
+    });
+}
+
+fn lookup(name: &str) -> Option<*const c_void> {
+    let function = match name {
+        "glCreateBuffers" => glCreateBuffers as *const c_void,
+        "glCreateProgram" => glCreateProgram as *const c_void,
+        "glCreateShader" => glCreateShader as *const c_void,
+        "glCreateVertexArrays" => glCreateVertexArrays as *const c_void,
+        "glGetActiveAttrib" => glGetActiveAttrib as *const c_void,
+        "glGetAttribLocation" => glGetAttribLocation as *const c_void,
+        "glGetError" => glGetError as *const c_void,
+        "glGetProgramiv" => glGetProgramiv as *const c_void,
+        "glGetShaderiv" => glGetShaderiv as *const c_void,
+        name => return stub(name),
+    };
+
+    Some(function)
+}
+
+fn record(name: &'static str, args: Vec<i64>) {
+    STATE.with_borrow_mut(|state| {
+        if state.failing == Some(name) {
+            state.failing = None;
+            state.errors.push(GL_INVALID_OPERATION);
+        }
+
+        state.calls.push((name, args));
+    });
+}
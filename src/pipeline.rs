@@ -0,0 +1,561 @@
+use crate::{context::Context, viewport::Rect, GlError};
+use gl46::{
+    BlendEquationModeEXT, BlendingFactor, EnableCap, GLenum, GL_ALWAYS, GL_BACK, GL_BLEND, GL_CCW,
+    GL_CONSTANT_ALPHA, GL_CONSTANT_COLOR, GL_CULL_FACE, GL_CW, GL_DECR, GL_DECR_WRAP,
+    GL_DEPTH_TEST, GL_DST_ALPHA, GL_DST_COLOR, GL_EQUAL, GL_FALSE, GL_FILL, GL_FRONT,
+    GL_FRONT_AND_BACK, GL_FUNC_ADD, GL_FUNC_REVERSE_SUBTRACT, GL_FUNC_SUBTRACT, GL_GEQUAL,
+    GL_GREATER, GL_INCR, GL_INCR_WRAP, GL_INVERT, GL_KEEP, GL_LEQUAL, GL_LESS, GL_LINE, GL_MAX,
+    GL_MIN, GL_NEVER, GL_NOTEQUAL, GL_ONE, GL_ONE_MINUS_CONSTANT_ALPHA,
+    GL_ONE_MINUS_CONSTANT_COLOR, GL_ONE_MINUS_DST_ALPHA, GL_ONE_MINUS_DST_COLOR,
+    GL_ONE_MINUS_SRC_ALPHA, GL_ONE_MINUS_SRC_COLOR, GL_POINT, GL_POLYGON_OFFSET_FILL,
+    GL_POLYGON_OFFSET_LINE, GL_POLYGON_OFFSET_POINT, GL_REPLACE, GL_SCISSOR_TEST, GL_SRC_ALPHA,
+    GL_SRC_ALPHA_SATURATE, GL_SRC_COLOR, GL_STENCIL_TEST, GL_TRUE, GL_ZERO,
+};
+use std::ffi::{c_int, c_uint};
+
+pub const MAX_COLOR_ATTACHMENTS: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendFactor {
+    Zero,
+    #[default]
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    ConstantColor,
+    OneMinusConstantColor,
+    ConstantAlpha,
+    OneMinusConstantAlpha,
+    SrcAlphaSaturate,
+}
+
+impl BlendFactor {
+    fn gl_type(self) -> BlendingFactor {
+        match self {
+            Self::Zero => GL_ZERO,
+            Self::One => GL_ONE,
+            Self::SrcColor => GL_SRC_COLOR,
+            Self::OneMinusSrcColor => GL_ONE_MINUS_SRC_COLOR,
+            Self::DstColor => GL_DST_COLOR,
+            Self::OneMinusDstColor => GL_ONE_MINUS_DST_COLOR,
+            Self::SrcAlpha => GL_SRC_ALPHA,
+            Self::OneMinusSrcAlpha => GL_ONE_MINUS_SRC_ALPHA,
+            Self::DstAlpha => GL_DST_ALPHA,
+            Self::OneMinusDstAlpha => GL_ONE_MINUS_DST_ALPHA,
+            Self::ConstantColor => GL_CONSTANT_COLOR,
+            Self::OneMinusConstantColor => GL_ONE_MINUS_CONSTANT_COLOR,
+            Self::ConstantAlpha => GL_CONSTANT_ALPHA,
+            Self::OneMinusConstantAlpha => GL_ONE_MINUS_CONSTANT_ALPHA,
+            Self::SrcAlphaSaturate => GL_SRC_ALPHA_SATURATE,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendOp {
+    #[default]
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendOp {
+    fn gl_type(self) -> BlendEquationModeEXT {
+        match self {
+            Self::Add => GL_FUNC_ADD,
+            Self::Subtract => GL_FUNC_SUBTRACT,
+            Self::ReverseSubtract => GL_FUNC_REVERSE_SUBTRACT,
+            Self::Min => GL_MIN,
+            Self::Max => GL_MAX,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BlendComponent {
+    pub src: BlendFactor,
+    pub dst: BlendFactor,
+    pub op: BlendOp,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Blend {
+    pub color: BlendComponent,
+    pub alpha: BlendComponent,
+}
+
+impl Blend {
+    pub const ALPHA: Self = Self {
+        color: BlendComponent {
+            src: BlendFactor::SrcAlpha,
+            dst: BlendFactor::OneMinusSrcAlpha,
+            op: BlendOp::Add,
+        },
+        alpha: BlendComponent {
+            src: BlendFactor::One,
+            dst: BlendFactor::OneMinusSrcAlpha,
+            op: BlendOp::Add,
+        },
+    };
+
+    pub const ADDITIVE: Self = Self {
+        color: BlendComponent {
+            src: BlendFactor::One,
+            dst: BlendFactor::One,
+            op: BlendOp::Add,
+        },
+        alpha: BlendComponent {
+            src: BlendFactor::One,
+            dst: BlendFactor::One,
+            op: BlendOp::Add,
+        },
+    };
+
+    pub const PREMULTIPLIED_ALPHA: Self = Self {
+        color: BlendComponent {
+            src: BlendFactor::One,
+            dst: BlendFactor::OneMinusSrcAlpha,
+            op: BlendOp::Add,
+        },
+        alpha: BlendComponent {
+            src: BlendFactor::One,
+            dst: BlendFactor::OneMinusSrcAlpha,
+            op: BlendOp::Add,
+        },
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ColorMask {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl ColorMask {
+    pub const ALL: Self = Self {
+        red: true,
+        green: true,
+        blue: true,
+        alpha: true,
+    };
+
+    pub const NONE: Self = Self {
+        red: false,
+        green: false,
+        blue: false,
+        alpha: false,
+    };
+}
+
+impl Default for ColorMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CompareFunction {
+    Never,
+    #[default]
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunction {
+    fn gl_type(self) -> GLenum {
+        match self {
+            Self::Never => GL_NEVER,
+            Self::Less => GL_LESS,
+            Self::Equal => GL_EQUAL,
+            Self::LessEqual => GL_LEQUAL,
+            Self::Greater => GL_GREATER,
+            Self::NotEqual => GL_NOTEQUAL,
+            Self::GreaterEqual => GL_GEQUAL,
+            Self::Always => GL_ALWAYS,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    None,
+    Front,
+    #[default]
+    Back,
+    FrontAndBack,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FrontFace {
+    #[default]
+    CounterClockwise,
+    Clockwise,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PolygonMode {
+    #[default]
+    Fill,
+    Line,
+    Point,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PolygonOffset {
+    pub factor: f32,
+    pub units: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Depth {
+    pub compare: CompareFunction,
+    pub test: bool,
+    pub write: bool,
+}
+
+impl Default for Depth {
+    fn default() -> Self {
+        Self {
+            compare: CompareFunction::Less,
+            test: true,
+            write: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum StencilOp {
+    #[default]
+    Keep,
+    Zero,
+    Replace,
+    Increment,
+    IncrementWrap,
+    Decrement,
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    fn gl_type(self) -> GLenum {
+        match self {
+            Self::Keep => GL_KEEP,
+            Self::Zero => GL_ZERO,
+            Self::Replace => GL_REPLACE,
+            Self::Increment => GL_INCR,
+            Self::IncrementWrap => GL_INCR_WRAP,
+            Self::Decrement => GL_DECR,
+            Self::DecrementWrap => GL_DECR_WRAP,
+            Self::Invert => GL_INVERT,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StencilFace {
+    pub compare: CompareFunction,
+    pub depth_fail: StencilOp,
+    pub fail: StencilOp,
+    pub pass: StencilOp,
+    pub read_mask: c_uint,
+    pub reference: c_int,
+    pub write_mask: c_uint,
+}
+
+impl Default for StencilFace {
+    fn default() -> Self {
+        Self {
+            compare: CompareFunction::Always,
+            depth_fail: StencilOp::Keep,
+            fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+            read_mask: !0,
+            reference: 0,
+            write_mask: !0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Stencil {
+    pub back: StencilFace,
+    pub front: StencilFace,
+}
+
+impl Stencil {
+    pub fn both(face: StencilFace) -> Self {
+        Self {
+            back: face,
+            front: face,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PipelineState {
+    pub blend: [Option<Blend>; MAX_COLOR_ATTACHMENTS],
+    pub blend_constant: [f32; 4],
+    pub color_mask: [ColorMask; MAX_COLOR_ATTACHMENTS],
+    pub cull_mode: CullMode,
+    pub depth: Depth,
+    pub front_face: FrontFace,
+    pub polygon_mode: PolygonMode,
+    pub polygon_offset: Option<PolygonOffset>,
+    pub scissor: Option<Rect>,
+    pub stencil: Option<Stencil>,
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        Self {
+            blend: [None; MAX_COLOR_ATTACHMENTS],
+            blend_constant: [0.0; 4],
+            color_mask: [ColorMask::ALL; MAX_COLOR_ATTACHMENTS],
+            cull_mode: CullMode::Back,
+            depth: Depth::default(),
+            front_face: FrontFace::CounterClockwise,
+            polygon_mode: PolygonMode::Fill,
+            polygon_offset: None,
+            scissor: None,
+            stencil: None,
+        }
+    }
+}
+
+impl PipelineState {
+    // Checks after every call, so an error names the state that failed.
+    #[track_caller]
+    pub(crate) fn apply(&self, gl: &Context, current: Option<&Self>) -> Result<(), GlError> {
+        unsafe {
+            for i in 0..MAX_COLOR_ATTACHMENTS {
+                if current.is_none_or(|current| current.blend[i] != self.blend[i]) {
+                    match self.blend[i] {
+                        Some(blend) => {
+                            gl.Enablei(GL_BLEND, i as _);
+                            gl.check("glEnablei")?;
+                            gl.BlendEquationSeparatei(
+                                i as _,
+                                blend.color.op.gl_type(),
+                                blend.alpha.op.gl_type(),
+                            );
+                            gl.check("glBlendEquationSeparatei")?;
+                            gl.BlendFuncSeparatei(
+                                i as _,
+                                blend.color.src.gl_type(),
+                                blend.color.dst.gl_type(),
+                                blend.alpha.src.gl_type(),
+                                blend.alpha.dst.gl_type(),
+                            );
+                            gl.check("glBlendFuncSeparatei")?;
+                        }
+                        None => {
+                            gl.Disablei(GL_BLEND, i as _);
+                            gl.check("glDisablei")?;
+                        }
+                    }
+                }
+
+                if current.is_none_or(|current| current.color_mask[i] != self.color_mask[i]) {
+                    let mask = self.color_mask[i];
+
+                    gl.ColorMaski(
+                        i as _,
+                        gl_bool(mask.red),
+                        gl_bool(mask.green),
+                        gl_bool(mask.blue),
+                        gl_bool(mask.alpha),
+                    );
+                    gl.check("glColorMaski")?;
+                }
+            }
+
+            if current.is_none_or(|current| current.blend_constant != self.blend_constant) {
+                let [r, g, b, a] = self.blend_constant;
+
+                gl.BlendColor(r, g, b, a);
+                gl.check("glBlendColor")?;
+            }
+
+            if current.is_none_or(|current| current.cull_mode != self.cull_mode) {
+                set_enabled(gl, GL_CULL_FACE, self.cull_mode != CullMode::None)?;
+
+                if self.cull_mode != CullMode::None {
+                    gl.CullFace(match self.cull_mode {
+                        CullMode::Front => GL_FRONT,
+                        CullMode::FrontAndBack => GL_FRONT_AND_BACK,
+                        _ => GL_BACK,
+                    });
+                    gl.check("glCullFace")?;
+                }
+            }
+
+            if current.is_none_or(|current| current.front_face != self.front_face) {
+                gl.FrontFace(match self.front_face {
+                    FrontFace::CounterClockwise => GL_CCW,
+                    FrontFace::Clockwise => GL_CW,
+                });
+                gl.check("glFrontFace")?;
+            }
+
+            if current.is_none_or(|current| current.depth != self.depth) {
+                set_enabled(gl, GL_DEPTH_TEST, self.depth.test)?;
+                gl.DepthFunc(self.depth.compare.gl_type());
+                gl.check("glDepthFunc")?;
+                gl.DepthMask(gl_bool(self.depth.write));
+                gl.check("glDepthMask")?;
+            }
+
+            if current.is_none_or(|current| current.stencil != self.stencil) {
+                set_enabled(gl, GL_STENCIL_TEST, self.stencil.is_some())?;
+
+                let stencil = self.stencil.unwrap_or_default();
+
+                for (face, state) in [(GL_FRONT, stencil.front), (GL_BACK, stencil.back)] {
+                    gl.StencilFuncSeparate(
+                        face,
+                        state.compare.gl_type(),
+                        state.reference,
+                        state.read_mask,
+                    );
+                    gl.check("glStencilFuncSeparate")?;
+                    gl.StencilOpSeparate(
+                        face,
+                        state.fail.gl_type(),
+                        state.depth_fail.gl_type(),
+                        state.pass.gl_type(),
+                    );
+                    gl.check("glStencilOpSeparate")?;
+                    gl.StencilMaskSeparate(face, state.write_mask);
+                    gl.check("glStencilMaskSeparate")?;
+                }
+            }
+
+            if current.is_none_or(|current| current.polygon_mode != self.polygon_mode) {
+                gl.PolygonMode(
+                    GL_FRONT_AND_BACK,
+                    match self.polygon_mode {
+                        PolygonMode::Fill => GL_FILL,
+                        PolygonMode::Line => GL_LINE,
+                        PolygonMode::Point => GL_POINT,
+                    },
+                );
+                gl.check("glPolygonMode")?;
+            }
+
+            if current.is_none_or(|current| current.polygon_offset != self.polygon_offset) {
+                for cap in [
+                    GL_POLYGON_OFFSET_FILL,
+                    GL_POLYGON_OFFSET_LINE,
+                    GL_POLYGON_OFFSET_POINT,
+                ] {
+                    set_enabled(gl, cap, self.polygon_offset.is_some())?;
+                }
+
+                if let Some(offset) = self.polygon_offset {
+                    gl.PolygonOffset(offset.factor, offset.units);
+                    gl.check("glPolygonOffset")?;
+                }
+            }
+
+            if current.is_none_or(|current| current.scissor != self.scissor) {
+                set_enabled(gl, GL_SCISSOR_TEST, self.scissor.is_some())?;
+
+                if let Some(rect) = self.scissor {
+                    gl.Scissor(rect.x, rect.y, rect.width, rect.height);
+                    gl.check("glScissor")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn with_blend(mut self, attachment: usize, blend: Option<Blend>) -> Self {
+        self.blend[attachment] = blend;
+
+        self
+    }
+
+    pub fn with_color_mask(mut self, attachment: usize, color_mask: ColorMask) -> Self {
+        self.color_mask[attachment] = color_mask;
+
+        self
+    }
+
+    pub fn with_cull_mode(mut self, cull_mode: CullMode) -> Self {
+        self.cull_mode = cull_mode;
+
+        self
+    }
+
+    pub fn with_depth(mut self, depth: Depth) -> Self {
+        self.depth = depth;
+
+        self
+    }
+
+    pub fn with_front_face(mut self, front_face: FrontFace) -> Self {
+        self.front_face = front_face;
+
+        self
+    }
+
+    pub fn with_polygon_mode(mut self, polygon_mode: PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+
+        self
+    }
+
+    pub fn with_polygon_offset(mut self, polygon_offset: Option<PolygonOffset>) -> Self {
+        self.polygon_offset = polygon_offset;
+
+        self
+    }
+
+    pub fn with_scissor(mut self, scissor: Option<Rect>) -> Self {
+        self.scissor = scissor;
+
+        self
+    }
+
+    pub fn with_stencil(mut self, stencil: Option<Stencil>) -> Self {
+        self.stencil = stencil;
+
+        self
+    }
+}
+
+fn gl_bool(value: bool) -> u8 {
+    if value {
+        GL_TRUE.0 as _
+    } else {
+        GL_FALSE.0 as _
+    }
+}
+
+// Bypasses the context's cache of enabled caps, which the pipeline owns.
+#[track_caller]
+unsafe fn set_enabled(gl: &Context, cap: EnableCap, enabled: bool) -> Result<(), GlError> {
+    if enabled {
+        gl.Enable(cap);
+    } else {
+        gl.Disable(cap);
+    }
+
+    gl.check(if enabled { "glEnable" } else { "glDisable" })
+}
@@ -0,0 +1,351 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    fs,
+    path::PathBuf,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PreprocessError {
+    #[error("{file}:{line}: cannot find included file \"{path}\"")]
+    IncludeNotFound {
+        file: String,
+        line: usize,
+        path: String,
+    },
+    #[error("{file}:{line}: malformed #include directive")]
+    MalformedInclude { file: String, line: usize },
+    #[error("{file}:{line}: \"{path}\" includes itself")]
+    RecursiveInclude {
+        file: String,
+        line: usize,
+        path: String,
+    },
+}
+
+pub trait SourceProvider {
+    fn load(&self, path: &str) -> Option<String>;
+}
+
+pub struct FileSourceProvider {
+    root: PathBuf,
+}
+
+impl FileSourceProvider {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+impl SourceProvider for FileSourceProvider {
+    fn load(&self, path: &str) -> Option<String> {
+        fs::read_to_string(self.path(path)).ok()
+    }
+}
+
+#[derive(Default)]
+pub struct MemorySourceProvider {
+    sources: HashMap<String, String>,
+}
+
+impl MemorySourceProvider {
+    pub fn insert(&mut self, path: impl Into<String>, src: impl Into<String>) {
+        self.sources.insert(path.into(), src.into());
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SourceProvider for MemorySourceProvider {
+    fn load(&self, path: &str) -> Option<String> {
+        self.sources.get(path).cloned()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreprocessedSource {
+    pub files: Vec<SourceFile>,
+    pub source: String,
+}
+
+impl PreprocessedSource {
+    pub fn file_name(&self, source_string: usize) -> Option<&str> {
+        self.files.get(source_string).map(|file| file.name.as_str())
+    }
+
+    pub fn line(&self, source_string: usize, line: usize) -> Option<&str> {
+        self.files
+            .get(source_string)?
+            .source
+            .lines()
+            .nth(line.checked_sub(1)?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+}
+
+pub struct Preprocessor<'a> {
+    defines: &'a [(Box<str>, Box<str>)],
+    provider: Option<&'a dyn SourceProvider>,
+}
+
+impl<'a> Preprocessor<'a> {
+    pub fn new(
+        defines: &'a [(Box<str>, Box<str>)],
+        provider: Option<&'a dyn SourceProvider>,
+    ) -> Self {
+        Self { defines, provider }
+    }
+
+    pub fn process(&self, name: &str, src: &str) -> Result<PreprocessedSource, PreprocessError> {
+        let mut output = Output {
+            body: String::with_capacity(src.len()),
+            files: vec![SourceFile {
+                name: name.to_owned(),
+                source: src.to_owned(),
+            }],
+            once: HashSet::new(),
+            stack: vec![name.to_owned()],
+            version: None,
+        };
+
+        writeln!(output.body, "#line 1 0").unwrap();
+        self.process_file(&mut output, 0, src)?;
+
+        let mut source = String::with_capacity(output.body.len() + 64);
+
+        if let Some(version) = &output.version {
+            writeln!(source, "{version}").unwrap();
+        }
+
+        for (name, value) in self.defines {
+            writeln!(source, "#define {name} {value}").unwrap();
+        }
+
+        source.push_str(&output.body);
+
+        Ok(PreprocessedSource {
+            files: output.files,
+            source,
+        })
+    }
+
+    fn process_file(
+        &self,
+        output: &mut Output,
+        file_index: usize,
+        src: &str,
+    ) -> Result<(), PreprocessError> {
+        for (i, line) in src.lines().enumerate() {
+            let line_number = i + 1;
+
+            match Directive::parse(line) {
+                Some(Directive::Version) => {
+                    if output.version.is_none() {
+                        output.version = Some(line.trim().to_owned());
+                    }
+
+                    output.body.push('\n');
+                }
+                Some(Directive::PragmaOnce) => {
+                    output.once.insert(output.files[file_index].name.clone());
+                    output.body.push('\n');
+                }
+                Some(Directive::Include(path)) => {
+                    let file = &output.files[file_index].name;
+                    let Some(path) = path else {
+                        return Err(PreprocessError::MalformedInclude {
+                            file: file.clone(),
+                            line: line_number,
+                        });
+                    };
+
+                    if output.stack.iter().any(|included| included == path) {
+                        return Err(PreprocessError::RecursiveInclude {
+                            file: file.clone(),
+                            line: line_number,
+                            path: path.to_owned(),
+                        });
+                    }
+
+                    if !output.once.contains(path) {
+                        let included = self
+                            .provider
+                            .and_then(|provider| provider.load(path))
+                            .ok_or_else(|| PreprocessError::IncludeNotFound {
+                                file: file.clone(),
+                                line: line_number,
+                                path: path.to_owned(),
+                            })?;
+
+                        let included_index = output
+                            .files
+                            .iter()
+                            .position(|file| file.name == path)
+                            .unwrap_or_else(|| {
+                                output.files.push(SourceFile {
+                                    name: path.to_owned(),
+                                    source: included.clone(),
+                                });
+                                output.files.len() - 1
+                            });
+
+                        writeln!(output.body, "#line 1 {included_index}").unwrap();
+                        output.stack.push(path.to_owned());
+                        self.process_file(output, included_index, &included)?;
+                        output.stack.pop();
+                    }
+
+                    writeln!(output.body, "#line {} {file_index}", line_number + 1).unwrap();
+                }
+                _ => {
+                    output.body.push_str(line);
+                    output.body.push('\n');
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Output {
+    body: String,
+    files: Vec<SourceFile>,
+    once: HashSet<String>,
+    stack: Vec<String>,
+    version: Option<String>,
+}
+
+enum Directive<'a> {
+    Include(Option<&'a str>),
+    PragmaOnce,
+    Version,
+}
+
+impl<'a> Directive<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let directive = line.trim_start().strip_prefix('#')?.trim_start();
+
+        if directive.starts_with("version") {
+            Some(Self::Version)
+        } else if let Some(rest) = directive.strip_prefix("include") {
+            let rest = rest.trim();
+            let path = rest
+                .strip_prefix('"')
+                .and_then(|rest| rest.strip_suffix('"'))
+                .or_else(|| {
+                    rest.strip_prefix('<')
+                        .and_then(|rest| rest.strip_suffix('>'))
+                })
+                .filter(|path| !path.is_empty());
+
+            Some(Self::Include(path))
+        } else if directive.strip_prefix("pragma")?.trim() == "once" {
+            Some(Self::PragmaOnce)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> MemorySourceProvider {
+        let mut provider = MemorySourceProvider::new();
+        provider.insert("lighting.glsl", "#pragma once\nfloat light() {\n}");
+        provider.insert("a.glsl", "#include \"b.glsl\"");
+        provider.insert("b.glsl", "#include \"a.glsl\"");
+        provider
+    }
+
+    #[test]
+    fn version_and_defines_come_first() {
+        let defines = [("LIGHTS".into(), "4".into())];
+        let result = Preprocessor::new(&defines, None)
+            .process("main", "// comment\n#version 460 core\nvoid main() {}")
+            .unwrap();
+
+        assert_eq!(
+            result.source,
+            "#version 460 core\n#define LIGHTS 4\n#line 1 0\n// comment\n\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn include_adds_line_directives() {
+        let provider = provider();
+        let result = Preprocessor::new(&[], Some(&provider))
+            .process(
+                "main",
+                "#version 460 core\n#include \"lighting.glsl\"\nvoid main() {}",
+            )
+            .unwrap();
+
+        assert_eq!(
+            result.source,
+            "#version 460 core\n#line 1 0\n\n#line 1 1\n\nfloat light() {\n}\n#line 3 0\nvoid main() {}\n"
+        );
+        assert_eq!(result.file_name(1), Some("lighting.glsl"));
+    }
+
+    #[test]
+    fn pragma_once_skips_second_include() {
+        let provider = provider();
+        let result = Preprocessor::new(&[], Some(&provider))
+            .process(
+                "main",
+                "#include \"lighting.glsl\"\n#include <lighting.glsl>",
+            )
+            .unwrap();
+
+        assert_eq!(result.source.matches("float light()").count(), 1);
+        assert_eq!(result.files.len(), 2);
+    }
+
+    #[test]
+    fn missing_include_is_reported() {
+        let error = Preprocessor::new(&[], None)
+            .process("main", "\n#include \"missing.glsl\"")
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            PreprocessError::IncludeNotFound { line: 2, ref path, .. } if path == "missing.glsl"
+        ));
+    }
+
+    #[test]
+    fn recursive_include_is_reported() {
+        let provider = provider();
+        let error = Preprocessor::new(&[], Some(&provider))
+            .process("main", "#include \"a.glsl\"")
+            .unwrap_err();
+
+        assert!(matches!(error, PreprocessError::RecursiveInclude { .. }));
+    }
+
+    #[test]
+    fn malformed_include_is_reported() {
+        let error = Preprocessor::new(&[], None)
+            .process("main", "#include lighting.glsl")
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            PreprocessError::MalformedInclude { line: 1, .. }
+        ));
+    }
+}
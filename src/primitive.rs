@@ -0,0 +1,53 @@
+use gl46::{
+    PrimitiveType, GL_LINES, GL_LINES_ADJACENCY, GL_LINE_LOOP, GL_LINE_STRIP,
+    GL_LINE_STRIP_ADJACENCY, GL_PATCHES, GL_POINTS, GL_TRIANGLES, GL_TRIANGLES_ADJACENCY,
+    GL_TRIANGLE_FAN, GL_TRIANGLE_STRIP, GL_TRIANGLE_STRIP_ADJACENCY,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    LinesAdjacency,
+    LineStripAdjacency,
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    TrianglesAdjacency,
+    TriangleStripAdjacency,
+    Patches,
+}
+
+impl Primitive {
+    pub(crate) fn gl_type(self) -> PrimitiveType {
+        match self {
+            Self::Points => GL_POINTS,
+            Self::Lines => GL_LINES,
+            Self::LineStrip => GL_LINE_STRIP,
+            Self::LineLoop => GL_LINE_LOOP,
+            Self::LinesAdjacency => GL_LINES_ADJACENCY,
+            Self::LineStripAdjacency => GL_LINE_STRIP_ADJACENCY,
+            Self::Triangles => GL_TRIANGLES,
+            Self::TriangleStrip => GL_TRIANGLE_STRIP,
+            Self::TriangleFan => GL_TRIANGLE_FAN,
+            Self::TrianglesAdjacency => GL_TRIANGLES_ADJACENCY,
+            Self::TriangleStripAdjacency => GL_TRIANGLE_STRIP_ADJACENCY,
+            Self::Patches => GL_PATCHES,
+        }
+    }
+
+    // Patches count as nothing, since only the tessellator knows what they
+    // turn into.
+    pub(crate) fn triangles(self, count: usize) -> usize {
+        match self {
+            Self::Triangles => count / 3,
+            Self::TriangleStrip | Self::TriangleFan => count.saturating_sub(2),
+            Self::TrianglesAdjacency => count / 6,
+            Self::TriangleStripAdjacency => count.saturating_sub(4) / 2,
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use crate::{
+    context::{Context, GlObject},
+    gl_error::GlError,
+    query::query_result,
+    stats::ResourceKind,
+};
+use gl46::GL_TIMESTAMP;
+use std::{
+    ffi::c_uint,
+    fmt,
+    mem::ManuallyDrop,
+    ops::Deref,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+// Results are read back one frame late so fetching them never stalls.
+const FRAMES: usize = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTiming {
+    pub cpu_time: Duration,
+    pub depth: usize,
+    pub gpu_time: Duration,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameReport {
+    pub cpu_time: Duration,
+    pub gpu_time: Duration,
+    pub passes: Vec<PassTiming>,
+}
+
+impl fmt::Display for FrameReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "frame: cpu {:.3} ms, gpu {:.3} ms",
+            millis(self.cpu_time),
+            millis(self.gpu_time)
+        )?;
+
+        for pass in &self.passes {
+            writeln!(
+                f,
+                "{:indent$}{}: cpu {:.3} ms, gpu {:.3} ms",
+                "",
+                pass.name,
+                millis(pass.cpu_time),
+                millis(pass.gpu_time),
+                indent = (pass.depth + 1) * 2
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Scope {
+    cpu_start: Instant,
+    cpu_time: Duration,
+    depth: usize,
+    end: c_uint,
+    name: String,
+    start: c_uint,
+}
+
+// Timestamp queries are pooled per frame and reused once the frame comes
+// around again.
+struct Frame {
+    cpu_start: Instant,
+    cpu_time: Duration,
+    end: c_uint,
+    pending: bool,
+    queries: Vec<c_uint>,
+    scopes: Vec<Scope>,
+    start: c_uint,
+    used: usize,
+}
+
+impl Frame {
+    fn new() -> Self {
+        Self {
+            cpu_start: Instant::now(),
+            cpu_time: Duration::ZERO,
+            end: 0,
+            pending: false,
+            queries: Vec::new(),
+            scopes: Vec::new(),
+            start: 0,
+            used: 0,
+        }
+    }
+
+    // `None` until every query of the frame has its result.
+    #[track_caller]
+    fn report(&self, gl: &Context) -> Result<Option<FrameReport>, GlError> {
+        let Some(gpu_time) = gpu_elapsed(gl, self.start, self.end)? else {
+            return Ok(None);
+        };
+
+        let mut passes = Vec::with_capacity(self.scopes.len());
+
+        for scope in &self.scopes {
+            let Some(pass_time) = gpu_elapsed(gl, scope.start, scope.end)? else {
+                return Ok(None);
+            };
+
+            passes.push(PassTiming {
+                cpu_time: scope.cpu_time,
+                depth: scope.depth,
+                gpu_time: pass_time,
+                name: scope.name.clone(),
+            });
+        }
+
+        Ok(Some(FrameReport {
+            cpu_time: self.cpu_time,
+            gpu_time,
+            passes,
+        }))
+    }
+
+    #[track_caller]
+    fn timestamp(&mut self, gl: &Context) -> Result<c_uint, GlError> {
+        if self.used == self.queries.len() {
+            let mut id = 0;
+
+            unsafe {
+                gl.CreateQueries(GL_TIMESTAMP, 1, &mut id);
+            }
+
+            gl.check("glCreateQueries")?;
+            gl.register(ResourceKind::Query, id, 0);
+
+            self.queries.push(id);
+        }
+
+        let id = self.queries[self.used];
+
+        self.used += 1;
+
+        unsafe {
+            gl.QueryCounter(id, GL_TIMESTAMP);
+        }
+
+        gl.check("glQueryCounter")?;
+
+        Ok(id)
+    }
+}
+
+pub struct Profiler {
+    current: usize,
+    frames: [Frame; FRAMES],
+    gl: Rc<Context>,
+    open: Vec<usize>,
+    report: Option<FrameReport>,
+}
+
+impl Profiler {
+    // A frame whose results still aren't available when its slot comes
+    // around again is dropped rather than waited on.
+    #[track_caller]
+    pub fn begin_frame(&mut self) -> Result<(), GlError> {
+        self.poll()?;
+        self.current = (self.current + 1) % FRAMES;
+        self.open.clear();
+
+        let frame = &mut self.frames[self.current];
+
+        frame.pending = false;
+        frame.scopes.clear();
+        frame.used = 0;
+        frame.cpu_start = Instant::now();
+        frame.start = frame.timestamp(&self.gl)?;
+
+        Ok(())
+    }
+
+    // Scopes double as debug groups, so passes show up named in frame
+    // captures.
+    #[track_caller]
+    pub fn begin_scope(&mut self, name: impl Into<String>) -> Result<(), GlError> {
+        let name = name.into();
+        let depth = self.open.len();
+
+        self.gl.push_debug_group(&name)?;
+
+        let frame = &mut self.frames[self.current];
+
+        // The group is popped again if the scope can't be opened, so groups
+        // stay balanced.
+        let start = match frame.timestamp(&self.gl) {
+            Ok(start) => start,
+            Err(error) => {
+                self.gl.pop_debug_group().ok();
+
+                return Err(error);
+            }
+        };
+
+        self.open.push(frame.scopes.len());
+        frame.scopes.push(Scope {
+            cpu_start: Instant::now(),
+            cpu_time: Duration::ZERO,
+            depth,
+            end: start,
+            name,
+            start,
+        });
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn end_frame(&mut self) -> Result<(), GlError> {
+        while !self.open.is_empty() {
+            self.end_scope()?;
+        }
+
+        let frame = &mut self.frames[self.current];
+
+        frame.end = frame.timestamp(&self.gl)?;
+        frame.cpu_time = frame.cpu_start.elapsed();
+        frame.pending = true;
+
+        self.poll()
+    }
+
+    #[track_caller]
+    pub fn end_scope(&mut self) -> Result<(), GlError> {
+        let Some(index) = self.open.pop() else {
+            return Ok(());
+        };
+
+        let frame = &mut self.frames[self.current];
+        let end = frame.timestamp(&self.gl);
+        let scope = &mut frame.scopes[index];
+
+        scope.cpu_time = scope.cpu_start.elapsed();
+
+        // A scope whose end couldn't be recorded reports no GPU time, and
+        // still pops its group.
+        match end {
+            Ok(end) => {
+                scope.end = end;
+                self.gl.pop_debug_group()
+            }
+            Err(error) => {
+                self.gl.pop_debug_group().ok();
+
+                Err(error)
+            }
+        }
+    }
+
+    pub(crate) fn new(gl: &Rc<Context>) -> Self {
+        Self {
+            current: 0,
+            frames: std::array::from_fn(|_| Frame::new()),
+            gl: gl.clone(),
+            open: Vec::new(),
+            report: None,
+        }
+    }
+
+    // The most recent frame whose GPU results have arrived.
+    pub fn report(&self) -> Option<&FrameReport> {
+        self.report.as_ref()
+    }
+
+    #[track_caller]
+    pub fn scope(&mut self, name: impl Into<String>) -> Result<ProfilerScope<'_>, GlError> {
+        self.begin_scope(name)?;
+
+        Ok(ProfilerScope { profiler: self })
+    }
+
+    // Oldest frame first, so the report ends up holding the newest one.
+    #[track_caller]
+    fn poll(&mut self) -> Result<(), GlError> {
+        for offset in 1..=FRAMES {
+            let frame = &mut self.frames[(self.current + offset) % FRAMES];
+
+            if !frame.pending {
+                continue;
+            }
+
+            if let Some(report) = frame.report(&self.gl)? {
+                frame.pending = false;
+                self.report = Some(report);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        for frame in &self.frames {
+            for query in &frame.queries {
+                self.gl.delete_later(GlObject::Query(*query));
+            }
+        }
+    }
+}
+
+// Ends its scope when dropped. Nested scopes are opened through the guard.
+pub struct ProfilerScope<'a> {
+    profiler: &'a mut Profiler,
+}
+
+impl ProfilerScope<'_> {
+    #[track_caller]
+    pub fn end(self) -> Result<(), GlError> {
+        let mut scope = ManuallyDrop::new(self);
+
+        scope.profiler.end_scope()
+    }
+
+    // The guard only hands out nested scopes, so the stack of open scopes
+    // can't be changed under it.
+    #[track_caller]
+    pub fn scope(&mut self, name: impl Into<String>) -> Result<ProfilerScope<'_>, GlError> {
+        self.profiler.scope(name)
+    }
+}
+
+impl Deref for ProfilerScope<'_> {
+    type Target = Profiler;
+
+    fn deref(&self) -> &Profiler {
+        self.profiler
+    }
+}
+
+impl Drop for ProfilerScope<'_> {
+    fn drop(&mut self) {
+        if !self.profiler.gl.is_lost() {
+            self.profiler.end_scope().ok();
+        }
+    }
+}
+
+#[track_caller]
+fn gpu_elapsed(gl: &Context, start: c_uint, end: c_uint) -> Result<Option<Duration>, GlError> {
+    let (Some(start), Some(end)) = (query_result(gl, start)?, query_result(gl, end)?) else {
+        return Ok(None);
+    };
+
+    Ok(Some(Duration::from_nanos(end.saturating_sub(start))))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -1,8 +1,29 @@
-use gl46::{GlFns, GL_ACTIVE_UNIFORMS, GL_BOOL, GL_FALSE};
+use crate::{
+    command_buffer::{next_generation, ProgramHandle},
+    context::{Context, GlObject},
+    diagnostics::ShaderLog,
+    gl_error::GlError,
+    preprocessor::{PreprocessError, PreprocessedSource, Preprocessor, SourceProvider},
+    primitive::Primitive,
+    program_cache::ProgramCache,
+    reflection::AttributeInfo,
+    renderer::Renderer,
+    spirv::SpirvShader,
+    stats::ResourceKind,
+};
+use gl46::{
+    GLenum, ShaderType, GL_ACTIVE_ATTRIBUTES, GL_ACTIVE_UNIFORMS, GL_BOOL, GL_FALSE, GL_FLOAT,
+    GL_FRAGMENT_SHADER, GL_GEOMETRY_SHADER, GL_PROGRAM_BINARY_LENGTH,
+    GL_PROGRAM_BINARY_RETRIEVABLE_HINT, GL_SHADER_BINARY_FORMAT_SPIR_V, GL_TESS_CONTROL_SHADER,
+    GL_TESS_EVALUATION_SHADER, GL_TRUE, GL_VERTEX_SHADER,
+};
 use std::{
     collections::HashMap,
     ffi::{c_int, c_uint},
+    io,
+    path::PathBuf,
     ptr::null_mut,
+    rc::Rc,
     string::FromUtf8Error,
 };
 
@@ -10,48 +31,252 @@ use std::{
 pub enum ProgramError {
     #[error("Failed to convert error message to string: {0}")]
     ErrorMsgConversion(#[from] FromUtf8Error),
+    #[error("Failed to preprocess shader: {0}")]
+    Preprocessing(#[from] PreprocessError),
     #[error("No uniform with name {0}")]
     NoSuchUniform(String),
-    #[error("Failed to link program: {0}")]
-    ProgramLinking(String),
+    #[error("Failed to link program:\n{0}")]
+    ProgramLinking(ShaderLog),
     #[error("Failed to create new program")]
     ProgramCreation,
-    #[error("Failed to compile shader: {0}")]
-    ShaderCompilation(String),
+    #[error("Failed to compile shader:\n{0}")]
+    ShaderCompilation(ShaderLog),
     #[error("Failed to create new shader")]
     ShaderCreation,
+    #[error("Failed to specialize {0} shader:\n{1}")]
+    ShaderSpecialization(ShaderStage, ShaderLog),
+    #[error("Failed to read shader source {0}: {1}")]
+    SourceRead(PathBuf, io::Error),
+    #[error(transparent)]
+    Gl(#[from] GlError),
 }
 
-pub struct ProgramBuilder<'a> {
-    fragment_src: &'a str,
-    gl: &'a GlFns,
-    vertex_src: &'a str,
+pub struct ProgramBuilder<'s> {
+    cache: Option<&'s ProgramCache>,
+    defines: Vec<(Box<str>, Box<str>)>,
+    fragment_src: Option<&'s str>,
+    geometry_src: Option<&'s str>,
+    gl: Rc<Context>,
+    patch_vertices: c_int,
+    source_names: Vec<(ShaderStage, &'s str)>,
+    source_provider: Option<&'s dyn SourceProvider>,
+    spirv: Vec<(ShaderStage, SpirvShader<'s>)>,
+    tess_control_src: Option<&'s str>,
+    tess_evaluation_src: Option<&'s str>,
+    vertex_src: Option<&'s str>,
 }
 
-pub struct Program<'a> {
-    gl: &'a GlFns,
+pub struct Program {
+    attributes: Vec<AttributeInfo>,
+    generation: u64,
+    gl: Rc<Context>,
     id: c_uint,
+    patch_vertices: Option<c_int>,
     uniforms: HashMap<Box<str>, UniformId>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, strum::Display)]
+pub enum ShaderStage {
+    #[strum(serialize = "vertex")]
+    Vertex,
+    #[strum(serialize = "tessellation control")]
+    TessControl,
+    #[strum(serialize = "tessellation evaluation")]
+    TessEvaluation,
+    #[strum(serialize = "geometry")]
+    Geometry,
+    #[strum(serialize = "fragment")]
+    Fragment,
+}
+
+impl ShaderStage {
+    pub(crate) fn gl_type(self) -> ShaderType {
+        match self {
+            Self::Vertex => GL_VERTEX_SHADER,
+            Self::TessControl => GL_TESS_CONTROL_SHADER,
+            Self::TessEvaluation => GL_TESS_EVALUATION_SHADER,
+            Self::Geometry => GL_GEOMETRY_SHADER,
+            Self::Fragment => GL_FRAGMENT_SHADER,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct UniformId(c_int);
 
-impl<'a> Program<'a> {
-    pub(crate) fn apply(&self) {
-        self.gl.UseProgram(self.id);
+impl Program {
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo> {
+        self.attributes
+            .iter()
+            .find(|attribute| &*attribute.name == name)
+    }
+
+    pub fn attributes(&self) -> &[AttributeInfo] {
+        &self.attributes
+    }
+
+    #[track_caller]
+    pub(crate) fn apply(&self) -> Result<(), GlError> {
+        self.gl.use_program(self.id)?;
+
+        match self.patch_vertices {
+            Some(patch_vertices) => self.gl.set_patch_vertices(patch_vertices),
+            None => Ok(()),
+        }
+    }
+
+    pub fn handle(&self) -> ProgramHandle {
+        ProgramHandle {
+            generation: self.generation,
+            id: self.id,
+        }
+    }
+
+    pub fn primitive(&self) -> Primitive {
+        if self.patch_vertices.is_some() {
+            Primitive::Patches
+        } else {
+            Primitive::Triangles
+        }
+    }
+
+    fn binary(&self) -> Option<(GLenum, Vec<u8>)> {
+        let mut len = 0;
+
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, GL_PROGRAM_BINARY_LENGTH, &mut len);
+        }
+
+        self.gl.check("glGetProgramiv").ok()?;
+
+        if len <= 0 {
+            return None;
+        }
+
+        let mut binary = vec![0u8; len as usize];
+        let mut format = GLenum(0);
+
+        unsafe {
+            self.gl.GetProgramBinary(
+                self.id,
+                len,
+                &mut len,
+                &mut format,
+                binary.as_mut_ptr().cast(),
+            );
+        }
+
+        self.gl.check("glGetProgramBinary").ok()?;
+        binary.truncate(len as usize);
+
+        Some((format, binary))
     }
 
-    pub fn set_uniform(&self, name: &str, value: &impl UniformType) -> bool {
-        self.uniforms
+    #[track_caller]
+    fn load_binary(&self, format: GLenum, binary: &[u8]) -> Result<bool, GlError> {
+        unsafe {
+            self.gl
+                .ProgramBinary(self.id, format, binary.as_ptr().cast(), binary.len() as _);
+        }
+
+        // A binary the driver rejects is relinked from source, so its error
+        // is cleared here rather than reported.
+        if self.gl.check("glProgramBinary").is_err() {
+            return Ok(false);
+        }
+
+        let mut link_result = GL_FALSE.0 as c_int;
+
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, gl46::GL_LINK_STATUS, &mut link_result);
+        }
+
+        self.gl.check("glGetProgramiv")?;
+
+        Ok(link_result != GL_FALSE.0 as c_int)
+    }
+
+    #[track_caller]
+    pub fn set_label(&self, label: &str) -> Result<(), GlError> {
+        self.gl.object_label(ResourceKind::Program, self.id, label)
+    }
+
+    #[track_caller]
+    pub fn set_uniform(
+        &self,
+        name: &str,
+        value: &(impl UniformType + ?Sized),
+    ) -> Result<(), ProgramError> {
+        let uniform_id = self
+            .uniforms
             .get(name)
-            .map(|uniform_id| {
-                value.set_uniform(self, *uniform_id);
-            })
-            .is_some()
+            .ok_or_else(|| ProgramError::NoSuchUniform(name.to_owned()))?;
+
+        value.set_uniform(self, *uniform_id)?;
+
+        Ok(())
     }
 
-    pub fn update_uniforms(&mut self) {
+    #[track_caller]
+    pub fn update_attributes(&mut self) -> Result<(), GlError> {
+        let mut count = 0;
+
+        unsafe {
+            self.gl
+                .GetProgramiv(self.id, GL_ACTIVE_ATTRIBUTES, &mut count);
+        }
+
+        self.gl.check("glGetProgramiv")?;
+
+        let mut attributes = Vec::with_capacity(count as _);
+
+        let mut name = [0u8; 128];
+        let mut len = 0;
+        let mut size = 0;
+        let mut type_ = GL_FLOAT;
+
+        for i in 0..count as _ {
+            unsafe {
+                self.gl.GetActiveAttrib(
+                    self.id,
+                    i,
+                    128,
+                    &mut len,
+                    &mut size,
+                    &mut type_,
+                    &mut name as _,
+                );
+            }
+
+            self.gl.check("glGetActiveAttrib")?;
+
+            let location = unsafe { self.gl.GetAttribLocation(self.id, name.as_ptr().cast()) };
+
+            self.gl.check("glGetAttribLocation")?;
+
+            // Built-in inputs such as gl_VertexID are active but have no location
+            if location < 0 {
+                continue;
+            }
+
+            let s = String::from_utf8_lossy(&name[..len as _])
+                .to_string()
+                .into_boxed_str();
+
+            attributes.extend(AttributeInfo::new(s, location as _, type_));
+        }
+
+        attributes.sort_by_key(|attribute| attribute.location);
+
+        self.attributes = attributes;
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn update_uniforms(&mut self) -> Result<(), GlError> {
         let mut count = 0;
 
         unsafe {
@@ -59,6 +284,8 @@ impl<'a> Program<'a> {
                 .GetProgramiv(self.id, GL_ACTIVE_UNIFORMS, &mut count);
         }
 
+        self.gl.check("glGetProgramiv")?;
+
         let mut uniforms = HashMap::with_capacity(count as _);
 
         let mut name = [0u8; 128];
@@ -79,64 +306,192 @@ impl<'a> Program<'a> {
                 );
             }
 
+            self.gl.check("glGetActiveUniform")?;
+
             let s = String::from_utf8_lossy(&name[..len as _])
                 .to_string()
                 .into_boxed_str();
+            let location = unsafe { self.gl.GetUniformLocation(self.id, name.as_ptr().cast()) };
 
-            uniforms.insert(
-                s,
-                UniformId(unsafe { self.gl.GetUniformLocation(self.id, name.as_ptr().cast()) }),
-            );
+            self.gl.check("glGetUniformLocation")?;
+            uniforms.insert(s, UniformId(location));
         }
+
         self.uniforms = uniforms;
+
+        Ok(())
     }
 
-    fn new(gl: &'a GlFns) -> Result<Self, ProgramError> {
+    #[track_caller]
+    fn new(gl: &Rc<Context>) -> Result<Self, ProgramError> {
         let id = gl.CreateProgram();
 
+        gl.check("glCreateProgram")?;
+
         let uniforms = HashMap::new();
 
+        if id != 0 {
+            gl.register(ResourceKind::Program, id, 0);
+        }
+
         (id != 0)
-            .then_some(Self { gl, id, uniforms })
+            .then_some(Self {
+                attributes: Vec::new(),
+                generation: next_generation(),
+                gl: gl.clone(),
+                id,
+                patch_vertices: None,
+                uniforms,
+            })
             .ok_or(ProgramError::ProgramCreation)
     }
 }
 
-impl Drop for Program<'_> {
+impl Drop for Program {
     fn drop(&mut self) {
-        self.gl.DeleteProgram(self.id);
+        self.gl.delete_later(GlObject::Program(self.id));
     }
 }
 
 pub struct Shader<'a> {
-    gl: &'a GlFns,
+    gl: &'a Context,
     id: c_uint,
 }
 
+// Dropping can't report an error, but clears it so it isn't blamed on the
+// next call.
 impl Drop for Shader<'_> {
     fn drop(&mut self) {
         self.gl.DeleteShader(self.id);
+        self.gl.check("glDeleteShader").ok();
     }
 }
 
-impl<'a> ProgramBuilder<'a> {
-    pub fn build(self) -> Result<Program<'a>, ProgramError> {
-        let mut program = Program::new(self.gl)?;
+impl<'s> ProgramBuilder<'s> {
+    #[track_caller]
+    pub fn build(self) -> Result<Program, ProgramError> {
+        self.build_with_sources().map(|(program, _)| program)
+    }
+
+    #[track_caller]
+    pub(crate) fn build_with_sources(
+        self,
+    ) -> Result<(Program, Vec<(ShaderStage, PreprocessedSource)>), ProgramError> {
+        let mut program = Program::new(&self.gl)?;
+
+        let stages = [
+            (ShaderStage::Vertex, self.vertex_src),
+            (ShaderStage::TessControl, self.tess_control_src),
+            (ShaderStage::TessEvaluation, self.tess_evaluation_src),
+            (ShaderStage::Geometry, self.geometry_src),
+            (ShaderStage::Fragment, self.fragment_src),
+        ];
+
+        let preprocessor = Preprocessor::new(&self.defines, self.source_provider);
+
+        let sources = stages
+            .into_iter()
+            .filter(|(stage, _)| {
+                !self
+                    .spirv
+                    .iter()
+                    .any(|(spirv_stage, _)| spirv_stage == stage)
+            })
+            .filter_map(|(stage, src)| src.map(|src| (stage, src)))
+            .map(|(stage, src)| {
+                let name = self
+                    .source_names
+                    .iter()
+                    .find(|(named_stage, _)| *named_stage == stage)
+                    .map_or_else(|| stage.to_string(), |(_, name)| name.to_string());
+
+                Ok((stage, preprocessor.process(&name, src)?))
+            })
+            .collect::<Result<Vec<_>, ProgramError>>()?;
+
+        let cache = match self.cache {
+            Some(cache) => Some((cache, ProgramCache::key(&self.gl, &sources, &self.spirv)?)),
+            None => None,
+        };
+
+        let cached = match cache.and_then(|(cache, key)| cache.load(key)) {
+            Some((format, binary)) => program.load_binary(format, &binary)?,
+            None => false,
+        };
+
+        if !cached {
+            if cache.is_some() {
+                unsafe {
+                    self.gl.ProgramParameteri(
+                        program.id,
+                        GL_PROGRAM_BINARY_RETRIEVABLE_HINT,
+                        GL_TRUE.0 as _,
+                    );
+                }
+
+                self.gl.check("glProgramParameteri")?;
+            }
+
+            self.link(&program, &sources)?;
+
+            if let Some(((cache, key), (format, binary))) = cache.zip(program.binary()) {
+                cache.store(key, format, &binary).ok();
+            }
+        }
 
-        let vertex = Self::compile_shader(self.gl, self.vertex_src, gl46::GL_VERTEX_SHADER)?;
-        let fragment = Self::compile_shader(self.gl, self.fragment_src, gl46::GL_FRAGMENT_SHADER)?;
+        let tessellated = self.tess_evaluation_src.is_some()
+            || self
+                .spirv
+                .iter()
+                .any(|(stage, _)| *stage == ShaderStage::TessEvaluation);
+
+        program.patch_vertices = tessellated.then_some(self.patch_vertices);
+        program.update_attributes()?;
+        program.update_uniforms()?;
+
+        Ok((program, sources))
+    }
+
+    // Loops rather than closures, so errors keep the caller's location.
+    #[track_caller]
+    fn link(
+        &self,
+        program: &Program,
+        sources: &[(ShaderStage, PreprocessedSource)],
+    ) -> Result<(), ProgramError> {
+        let mut shaders = Vec::with_capacity(sources.len() + self.spirv.len());
+
+        for (stage, source) in sources {
+            shaders.push(Self::compile_shader(&self.gl, source, *stage)?);
+        }
+
+        for (stage, shader) in &self.spirv {
+            shaders.push(Self::specialize_shader(&self.gl, shader, *stage)?);
+        }
+
+        for shader in &shaders {
+            self.gl.AttachShader(program.id, shader.id);
+            self.gl.check("glAttachShader")?;
+        }
 
-        self.gl.AttachShader(program.id, vertex.id);
-        self.gl.AttachShader(program.id, fragment.id);
         self.gl.LinkProgram(program.id);
+        self.gl.check("glLinkProgram")?;
 
         let mut compilation_result = gl46::GL_FALSE.0 as c_int;
 
         unsafe {
             self.gl
                 .GetProgramiv(program.id, gl46::GL_LINK_STATUS, &mut compilation_result);
-            self.gl.DetachShader(program.id, vertex.id);
-            self.gl.DetachShader(program.id, fragment.id);
+        }
+
+        self.gl.check("glGetProgramiv")?;
+
+        for shader in &shaders {
+            unsafe {
+                self.gl.DetachShader(program.id, shader.id);
+            }
+
+            self.gl.check("glDetachShader")?;
         }
 
         if compilation_result == gl46::GL_FALSE.0 as c_int {
@@ -147,6 +502,8 @@ impl<'a> ProgramBuilder<'a> {
                     .GetProgramiv(program.id, gl46::GL_INFO_LOG_LENGTH, &mut info_len);
             }
 
+            self.gl.check("glGetProgramiv")?;
+
             let mut error = vec![0; info_len as usize];
 
             unsafe {
@@ -154,89 +511,291 @@ impl<'a> ProgramBuilder<'a> {
                     .GetProgramInfoLog(program.id, info_len, null_mut(), error.as_mut_ptr());
             }
 
-            return Err(ProgramError::ProgramLinking(String::from_utf8(error)?));
+            self.gl.check("glGetProgramInfoLog")?;
+
+            return Err(ProgramError::ProgramLinking(ShaderLog::parse(
+                &String::from_utf8(error)?,
+                None,
+                sources.to_vec(),
+            )));
         }
 
-        program.update_uniforms();
+        Ok(())
+    }
 
-        Ok(program)
+    pub fn from_spirv(
+        renderer: &Renderer,
+        vertex: SpirvShader<'s>,
+        fragment: SpirvShader<'s>,
+    ) -> Self {
+        Self {
+            cache: None,
+            defines: Vec::new(),
+            fragment_src: None,
+            geometry_src: None,
+            gl: renderer.gl.clone(),
+            patch_vertices: 3,
+            source_names: Vec::new(),
+            source_provider: None,
+            spirv: Vec::new(),
+            tess_control_src: None,
+            tess_evaluation_src: None,
+            vertex_src: None,
+        }
+        .with_spirv(ShaderStage::Vertex, vertex)
+        .with_spirv(ShaderStage::Fragment, fragment)
     }
 
-    pub fn new(gl: &'a GlFns, vertex_src: &'a str, fragment_src: &'a str) -> Self {
+    pub(crate) fn from_context(
+        gl: Rc<Context>,
+        vertex_src: &'s str,
+        fragment_src: &'s str,
+    ) -> Self {
         Self {
-            fragment_src,
+            cache: None,
+            defines: Vec::new(),
+            fragment_src: Some(fragment_src),
+            geometry_src: None,
             gl,
-            vertex_src,
+            patch_vertices: 3,
+            source_names: Vec::new(),
+            source_provider: None,
+            spirv: Vec::new(),
+            tess_control_src: None,
+            tess_evaluation_src: None,
+            vertex_src: Some(vertex_src),
         }
     }
 
-    fn compile_shader(
-        gl: &'a GlFns,
-        src: &str,
-        shader_type: gl46::GLenum,
+    pub fn new(renderer: &Renderer, vertex_src: &'s str, fragment_src: &'s str) -> Self {
+        Self::from_context(renderer.gl.clone(), vertex_src, fragment_src)
+    }
+
+    pub fn with_cache(mut self, cache: &'s ProgramCache) -> Self {
+        self.cache = Some(cache);
+
+        self
+    }
+
+    pub fn with_define(mut self, name: &str, value: impl ToString) -> Self {
+        self.defines
+            .push((name.into(), value.to_string().into_boxed_str()));
+
+        self
+    }
+
+    pub fn with_geometry(mut self, geometry_src: &'s str) -> Self {
+        self.geometry_src = Some(geometry_src);
+
+        self
+    }
+
+    pub fn with_patch_vertices(mut self, patch_vertices: c_int) -> Self {
+        self.patch_vertices = patch_vertices;
+
+        self
+    }
+
+    pub(crate) fn with_source_name(mut self, stage: ShaderStage, name: &'s str) -> Self {
+        self.source_names.push((stage, name));
+
+        self
+    }
+
+    pub fn with_source_provider(mut self, source_provider: &'s dyn SourceProvider) -> Self {
+        self.source_provider = Some(source_provider);
+
+        self
+    }
+
+    pub fn with_spirv(mut self, stage: ShaderStage, shader: SpirvShader<'s>) -> Self {
+        self.spirv.retain(|(existing, _)| *existing != stage);
+        self.spirv.push((stage, shader));
+
+        self
+    }
+
+    pub fn with_tess_control(mut self, tess_control_src: &'s str) -> Self {
+        self.tess_control_src = Some(tess_control_src);
+
+        self
+    }
+
+    pub fn with_tess_evaluation(mut self, tess_evaluation_src: &'s str) -> Self {
+        self.tess_evaluation_src = Some(tess_evaluation_src);
+
+        self
+    }
+
+    #[track_caller]
+    fn compile_shader<'a>(
+        gl: &'a Context,
+        source: &PreprocessedSource,
+        stage: ShaderStage,
     ) -> Result<Shader<'a>, ProgramError> {
-        let shader = gl.CreateShader(shader_type);
+        let id = gl.CreateShader(stage.gl_type());
+
+        gl.check("glCreateShader")?;
 
-        if shader == 0 {
+        if id == 0 {
             return Err(ProgramError::ShaderCreation);
         }
 
+        let shader = Shader { gl, id };
+        let src = &source.source;
+
         unsafe {
-            gl.ShaderSource(shader, 1, &src.as_ptr(), &(src.len() as c_int));
+            gl.ShaderSource(id, 1, &src.as_ptr(), &(src.len() as c_int));
         };
 
-        gl.CompileShader(shader);
+        gl.check("glShaderSource")?;
+        gl.CompileShader(id);
+        gl.check("glCompileShader")?;
 
         let mut compilation_result = gl46::GL_FALSE.0 as c_int;
 
         unsafe {
-            gl.GetShaderiv(shader, gl46::GL_COMPILE_STATUS, &mut compilation_result);
+            gl.GetShaderiv(id, gl46::GL_COMPILE_STATUS, &mut compilation_result);
         }
 
+        gl.check("glGetShaderiv")?;
+
         if compilation_result == gl46::GL_FALSE.0 as c_int {
             let mut info_len = 0;
 
             unsafe {
-                gl.GetShaderiv(shader, gl46::GL_INFO_LOG_LENGTH, &mut info_len);
+                gl.GetShaderiv(id, gl46::GL_INFO_LOG_LENGTH, &mut info_len);
             }
 
+            gl.check("glGetShaderiv")?;
+
             let mut error = vec![0; info_len as usize];
 
             unsafe {
-                gl.GetShaderInfoLog(shader, info_len, null_mut(), error.as_mut_ptr());
+                gl.GetShaderInfoLog(id, info_len, null_mut(), error.as_mut_ptr());
             }
 
-            return Err(ProgramError::ShaderCompilation(String::from_utf8(error)?));
+            gl.check("glGetShaderInfoLog")?;
+
+            return Err(ProgramError::ShaderCompilation(ShaderLog::parse(
+                &String::from_utf8(error)?,
+                Some(stage),
+                vec![(stage, source.clone())],
+            )));
         }
 
-        Ok(Shader { gl, id: shader })
+        Ok(shader)
+    }
+
+    #[track_caller]
+    fn specialize_shader<'a>(
+        gl: &'a Context,
+        shader: &SpirvShader,
+        stage: ShaderStage,
+    ) -> Result<Shader<'a>, ProgramError> {
+        let id = gl.CreateShader(stage.gl_type());
+
+        gl.check("glCreateShader")?;
+
+        if id == 0 {
+            return Err(ProgramError::ShaderCreation);
+        }
+
+        let spirv = Shader { gl, id };
+        let (indices, values): (Vec<_>, Vec<_>) = shader.constants().iter().copied().unzip();
+
+        unsafe {
+            gl.ShaderBinary(
+                1,
+                &id,
+                GL_SHADER_BINARY_FORMAT_SPIR_V,
+                shader.binary().as_ptr().cast(),
+                shader.binary().len() as _,
+            );
+        }
+
+        gl.check("glShaderBinary")?;
+
+        unsafe {
+            gl.SpecializeShader(
+                id,
+                shader.entry_point().as_ptr().cast(),
+                indices.len() as _,
+                indices.as_ptr(),
+                values.as_ptr(),
+            );
+        }
+
+        gl.check("glSpecializeShader")?;
+
+        let mut specialization_result = gl46::GL_FALSE.0 as c_int;
+
+        unsafe {
+            gl.GetShaderiv(id, gl46::GL_COMPILE_STATUS, &mut specialization_result);
+        }
+
+        gl.check("glGetShaderiv")?;
+
+        if specialization_result == gl46::GL_FALSE.0 as c_int {
+            let mut info_len = 0;
+
+            unsafe {
+                gl.GetShaderiv(id, gl46::GL_INFO_LOG_LENGTH, &mut info_len);
+            }
+
+            gl.check("glGetShaderiv")?;
+
+            let mut error = vec![0; info_len as usize];
+
+            unsafe {
+                gl.GetShaderInfoLog(id, info_len, null_mut(), error.as_mut_ptr());
+            }
+
+            gl.check("glGetShaderInfoLog")?;
+
+            return Err(ProgramError::ShaderSpecialization(
+                stage,
+                ShaderLog::parse(&String::from_utf8(error)?, Some(stage), Vec::new()),
+            ));
+        }
+
+        Ok(spirv)
     }
 }
 
+// Each type checks the GL call it makes, so the error names it.
 pub trait UniformType {
-    fn set_uniform(&self, program: &Program, uniform_id: UniformId);
+    #[track_caller]
+    fn set_uniform(&self, program: &Program, uniform_id: UniformId) -> Result<(), GlError>;
 }
 
 impl UniformType for f32 {
-    fn set_uniform(&self, program: &Program, uniform_id: UniformId) {
+    #[track_caller]
+    fn set_uniform(&self, program: &Program, uniform_id: UniformId) -> Result<(), GlError> {
         unsafe {
             program.gl.ProgramUniform1f(program.id, uniform_id.0, *self);
         }
+
+        program.gl.check("glProgramUniform1f")
     }
 }
 
 impl UniformType for glam::Vec2 {
-    fn set_uniform(&self, program: &Program, uniform_id: UniformId) {
+    #[track_caller]
+    fn set_uniform(&self, program: &Program, uniform_id: UniformId) -> Result<(), GlError> {
         unsafe {
             program
                 .gl
                 .ProgramUniform2fv(program.id, uniform_id.0, 1, self.to_array().as_ptr());
         }
+
+        program.gl.check("glProgramUniform2fv")
     }
 }
 
 impl UniformType for glam::Mat4 {
-    fn set_uniform(&self, program: &Program, uniform_id: UniformId) {
+    #[track_caller]
+    fn set_uniform(&self, program: &Program, uniform_id: UniformId) -> Result<(), GlError> {
         unsafe {
             program.gl.ProgramUniformMatrix4fv(
                 program.id,
@@ -246,5 +805,7 @@ impl UniformType for glam::Mat4 {
                 self.to_cols_array().as_ptr(),
             )
         }
+
+        program.gl.check("glProgramUniformMatrix4fv")
     }
 }
@@ -0,0 +1,128 @@
+use crate::{
+    context::Context, gl_error::GlError, preprocessor::PreprocessedSource, program::ShaderStage,
+    spirv::SpirvShader,
+};
+use gl46::{GLenum, StringName, GL_RENDERER, GL_VENDOR, GL_VERSION};
+use std::{
+    ffi::CStr,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub struct ProgramCache {
+    dir: PathBuf,
+}
+
+impl ProgramCache {
+    pub fn clear(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+
+            if path.extension().is_some_and(|extension| extension == "bin") {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir })
+    }
+
+    #[track_caller]
+    pub(crate) fn key(
+        gl: &Context,
+        sources: &[(ShaderStage, PreprocessedSource)],
+        spirv: &[(ShaderStage, SpirvShader)],
+    ) -> Result<u64, GlError> {
+        let mut hash = Fnv1a::new();
+
+        for name in [GL_VENDOR, GL_RENDERER, GL_VERSION] {
+            hash.write(driver_string(gl, name)?.to_bytes());
+            hash.write(&[0]);
+        }
+
+        for (stage, source) in sources {
+            hash.write(&[*stage as u8]);
+            hash.write(source.source.as_bytes());
+            hash.write(&[0]);
+        }
+
+        for (stage, shader) in spirv {
+            hash.write(&[*stage as u8]);
+            hash.write(shader.binary());
+            hash.write(shader.entry_point().as_bytes_with_nul());
+
+            for (index, value) in shader.constants() {
+                hash.write(&index.to_le_bytes());
+                hash.write(&value.to_le_bytes());
+            }
+        }
+
+        Ok(hash.finish())
+    }
+
+    pub(crate) fn load(&self, key: u64) -> Option<(GLenum, Vec<u8>)> {
+        let data = fs::read(self.path(key)).ok()?;
+        let (format, binary) = data.split_first_chunk::<4>()?;
+
+        Some((GLenum(u32::from_le_bytes(*format)), binary.to_vec()))
+    }
+
+    pub(crate) fn store(&self, key: u64, format: GLenum, binary: &[u8]) -> io::Result<()> {
+        let mut data = Vec::with_capacity(binary.len() + 4);
+
+        data.extend_from_slice(&format.0.to_le_bytes());
+        data.extend_from_slice(binary);
+
+        let path = self.path(key);
+        let tmp = path.with_extension("tmp");
+
+        fs::write(&tmp, data)?;
+        fs::rename(tmp, path)
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{key:016x}.bin"))
+    }
+}
+
+#[track_caller]
+fn driver_string(gl: &Context, name: StringName) -> Result<&CStr, GlError> {
+    let ptr = unsafe { gl.GetString(name) };
+
+    gl.check("glGetString")?;
+
+    if ptr.is_null() {
+        Ok(Default::default())
+    } else {
+        Ok(unsafe { CStr::from_ptr(ptr.cast()) })
+    }
+}
+
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{
+    context::{Context, GlObject},
+    gl_error::GlError,
+    stats::ResourceKind,
+};
+use gl46::{
+    QueryTarget, GL_ANY_SAMPLES_PASSED, GL_ANY_SAMPLES_PASSED_CONSERVATIVE,
+    GL_PRIMITIVES_GENERATED, GL_QUERY_RESULT, GL_QUERY_RESULT_AVAILABLE, GL_SAMPLES_PASSED,
+    GL_TIME_ELAPSED,
+};
+use std::{ffi::c_uint, rc::Rc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    AnySamplesPassed,
+    AnySamplesPassedConservative,
+    PrimitivesGenerated,
+    SamplesPassed,
+    TimeElapsed,
+}
+
+impl QueryKind {
+    fn gl_type(self) -> QueryTarget {
+        match self {
+            Self::AnySamplesPassed => GL_ANY_SAMPLES_PASSED,
+            Self::AnySamplesPassedConservative => GL_ANY_SAMPLES_PASSED_CONSERVATIVE,
+            Self::PrimitivesGenerated => GL_PRIMITIVES_GENERATED,
+            Self::SamplesPassed => GL_SAMPLES_PASSED,
+            Self::TimeElapsed => GL_TIME_ELAPSED,
+        }
+    }
+}
+
+// Only one query of each kind can be active at a time. Elapsed time is
+// reported in nanoseconds.
+pub struct Query {
+    gl: Rc<Context>,
+    id: c_uint,
+    kind: QueryKind,
+}
+
+impl Query {
+    #[track_caller]
+    pub fn begin(&self) -> Result<(), GlError> {
+        unsafe {
+            self.gl.BeginQuery(self.kind.gl_type(), self.id);
+        }
+
+        self.gl.check("glBeginQuery")
+    }
+
+    #[track_caller]
+    pub fn end(&self) -> Result<(), GlError> {
+        unsafe {
+            self.gl.EndQuery(self.kind.gl_type());
+        }
+
+        self.gl.check("glEndQuery")
+    }
+
+    pub fn kind(&self) -> QueryKind {
+        self.kind
+    }
+
+    #[track_caller]
+    pub(crate) fn new(gl: &Rc<Context>, kind: QueryKind) -> Result<Self, GlError> {
+        let mut id = 0;
+
+        unsafe {
+            gl.CreateQueries(kind.gl_type(), 1, &mut id);
+        }
+
+        gl.check("glCreateQueries")?;
+        gl.register(ResourceKind::Query, id, 0);
+
+        Ok(Self {
+            gl: gl.clone(),
+            id,
+            kind,
+        })
+    }
+
+    // Returns `None` while the GPU hasn't produced the result yet.
+    #[track_caller]
+    pub fn result(&self) -> Result<Option<u64>, GlError> {
+        query_result(&self.gl, self.id)
+    }
+
+    #[track_caller]
+    pub fn wait_result(&self) -> Result<u64, GlError> {
+        let mut result = 0;
+
+        unsafe {
+            self.gl
+                .GetQueryObjectui64v(self.id, GL_QUERY_RESULT, &mut result);
+        }
+
+        self.gl.check("glGetQueryObjectui64v")?;
+
+        Ok(result)
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        self.gl.delete_later(GlObject::Query(self.id));
+    }
+}
+
+#[track_caller]
+pub(crate) fn query_result(gl: &Context, id: c_uint) -> Result<Option<u64>, GlError> {
+    let mut available = 0;
+    let mut result = 0;
+
+    unsafe {
+        gl.GetQueryObjectiv(id, GL_QUERY_RESULT_AVAILABLE, &mut available);
+    }
+
+    gl.check("glGetQueryObjectiv")?;
+
+    if available == 0 {
+        return Ok(None);
+    }
+
+    unsafe {
+        gl.GetQueryObjectui64v(id, GL_QUERY_RESULT, &mut result);
+    }
+
+    gl.check("glGetQueryObjectui64v")?;
+
+    Ok(Some(result))
+}
@@ -0,0 +1,122 @@
+use gl46::{
+    AttributeType, GL_DOUBLE, GL_DOUBLE_MAT2, GL_DOUBLE_MAT3, GL_DOUBLE_MAT4, GL_DOUBLE_VEC2,
+    GL_DOUBLE_VEC3, GL_DOUBLE_VEC4, GL_FLOAT, GL_FLOAT_MAT2, GL_FLOAT_MAT3, GL_FLOAT_MAT4,
+    GL_FLOAT_VEC2, GL_FLOAT_VEC3, GL_FLOAT_VEC4, GL_INT, GL_INT_VEC2, GL_INT_VEC3, GL_INT_VEC4,
+    GL_UNSIGNED_INT, GL_UNSIGNED_INT_VEC2, GL_UNSIGNED_INT_VEC3, GL_UNSIGNED_INT_VEC4,
+};
+use std::ffi::c_uint;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+pub enum ComponentType {
+    #[strum(serialize = "double")]
+    Double,
+    #[strum(serialize = "float")]
+    Float,
+    #[strum(serialize = "int")]
+    Int,
+    #[strum(serialize = "uint")]
+    UnsignedInt,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttributeInfo {
+    pub columns: usize,
+    pub components: usize,
+    pub component_type: ComponentType,
+    pub location: c_uint,
+    pub name: Box<str>,
+}
+
+impl AttributeInfo {
+    pub(crate) fn covers(&self, location: c_uint) -> bool {
+        (self.location..self.location + self.columns as c_uint).contains(&location)
+    }
+
+    pub(crate) fn new(name: Box<str>, location: c_uint, type_: AttributeType) -> Option<Self> {
+        let (component_type, components, columns) = match type_ {
+            GL_FLOAT => (ComponentType::Float, 1, 1),
+            GL_FLOAT_VEC2 => (ComponentType::Float, 2, 1),
+            GL_FLOAT_VEC3 => (ComponentType::Float, 3, 1),
+            GL_FLOAT_VEC4 => (ComponentType::Float, 4, 1),
+            GL_FLOAT_MAT2 => (ComponentType::Float, 2, 2),
+            GL_FLOAT_MAT3 => (ComponentType::Float, 3, 3),
+            GL_FLOAT_MAT4 => (ComponentType::Float, 4, 4),
+            GL_DOUBLE => (ComponentType::Double, 1, 1),
+            GL_DOUBLE_VEC2 => (ComponentType::Double, 2, 1),
+            GL_DOUBLE_VEC3 => (ComponentType::Double, 3, 1),
+            GL_DOUBLE_VEC4 => (ComponentType::Double, 4, 1),
+            GL_DOUBLE_MAT2 => (ComponentType::Double, 2, 2),
+            GL_DOUBLE_MAT3 => (ComponentType::Double, 3, 3),
+            GL_DOUBLE_MAT4 => (ComponentType::Double, 4, 4),
+            GL_INT => (ComponentType::Int, 1, 1),
+            GL_INT_VEC2 => (ComponentType::Int, 2, 1),
+            GL_INT_VEC3 => (ComponentType::Int, 3, 1),
+            GL_INT_VEC4 => (ComponentType::Int, 4, 1),
+            GL_UNSIGNED_INT => (ComponentType::UnsignedInt, 1, 1),
+            GL_UNSIGNED_INT_VEC2 => (ComponentType::UnsignedInt, 2, 1),
+            GL_UNSIGNED_INT_VEC3 => (ComponentType::UnsignedInt, 3, 1),
+            GL_UNSIGNED_INT_VEC4 => (ComponentType::UnsignedInt, 4, 1),
+            _ => return None,
+        };
+
+        Some(Self {
+            columns,
+            components,
+            component_type,
+            location,
+            name,
+        })
+    }
+
+    pub(crate) fn validate(
+        &self,
+        component_type: ComponentType,
+        components: usize,
+    ) -> Result<(), VertexLayoutError> {
+        self.validate_components(component_type, components, self.components)
+    }
+
+    // A buffer bound by name holds whole values, so a matrix buffer carries
+    // the components of every column.
+    pub(crate) fn validate_value(
+        &self,
+        component_type: ComponentType,
+        components: usize,
+    ) -> Result<(), VertexLayoutError> {
+        self.validate_components(component_type, components, self.components * self.columns)
+    }
+
+    fn validate_components(
+        &self,
+        component_type: ComponentType,
+        components: usize,
+        expected: usize,
+    ) -> Result<(), VertexLayoutError> {
+        if component_type != self.component_type || components != expected {
+            return Err(VertexLayoutError::Mismatch {
+                name: self.name.clone(),
+                expected: (self.component_type, expected),
+                found: (component_type, components),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum VertexLayoutError {
+    #[error(
+        "Attribute {name} expects {} {} components, but the buffer has {} {}",
+        .expected.1, .expected.0, .found.1, .found.0
+    )]
+    Mismatch {
+        name: Box<str>,
+        expected: (ComponentType, usize),
+        found: (ComponentType, usize),
+    },
+    #[error("Attribute {0} has no vertex buffer")]
+    MissingBuffer(Box<str>),
+    #[error("No active attribute with name {0}")]
+    NoSuchAttribute(String),
+}
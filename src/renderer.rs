@@ -1,116 +1,1398 @@
 use crate::{
+    buffer::BufferStorage,
+    clear::{ClearBuffers, ClearColor},
+    command_buffer::{Command, CommandBuffer, SubmitError},
+    context::{Context, GlObject, VertexAttribState},
+    debug::{DebugFilter, DebugGroup, DebugMessage},
+    fence::Fence,
+    gl_error::GlError,
+    hot_reload::{ShaderFiles, WatchedProgram},
     indices::IndexType,
-    program::Program,
+    indirect::{
+        DrawArraysIndirectCommand, DrawCountBuffer, DrawElementsIndirectCommand,
+        DrawIndirectBuffer, IndirectCommand,
+    },
+    math::Vec4,
+    mesh::Mesh,
+    pipeline::{ColorMask, PipelineState, MAX_COLOR_ATTACHMENTS},
+    primitive::Primitive,
+    profiler::Profiler,
+    program::{Program, ProgramError},
+    query::{Query, QueryKind},
+    reflection::{AttributeInfo, VertexLayoutError},
+    ring_buffer::{RingBuffer, RingBufferError, RingRange},
+    spirv::SpirvShader,
+    stats::{FrameStats, RendererStats, Resource, ResourceKind},
     vertices::{VertexAttribute, VertexAttributeType},
+    viewport::{Rect, Viewport},
     Indices, ProgramBuilder, Vertices,
 };
-use gl46::{GlFns, GL_COLOR_BUFFER_BIT, GL_DEPTH_BUFFER_BIT, GL_TRIANGLES};
-use std::{ffi::c_uint, marker::PhantomData, ptr::null};
+use gl46::{
+    GlFns, GL_CONTEXT_FLAGS, GL_CONTEXT_FLAG_DEBUG_BIT, GL_DEPTH, GL_DEPTH_STENCIL,
+    GL_ELEMENT_ARRAY_BUFFER, GL_FRAMEBUFFER_SRGB, GL_STENCIL, GL_UNIFORM_BUFFER,
+};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{c_int, c_uint},
+    marker::PhantomData,
+    ptr::null,
+    rc::Rc,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DrawError {
+    #[error("Invalid vertex layout: {0}")]
+    Layout(#[from] VertexLayoutError),
+    #[error(transparent)]
+    Gl(#[from] GlError),
+    #[error("Draw range of {count} elements at {first} exceeds {len} elements")]
+    RangeOutOfBounds {
+        count: usize,
+        first: usize,
+        len: usize,
+    },
+    #[error("Indirect draws take their index range from the commands")]
+    IndirectIndexRange,
+}
 
 pub struct Renderer {
-    pub(crate) gl: GlFns,
+    pub(crate) gl: Rc<Context>,
+    last_frame: Cell<FrameStats>,
+    vertex_array: c_uint,
 }
 
 impl Renderer {
-    pub fn clear(&self) {
+    #[track_caller]
+    pub fn bind_uniform_range<T>(
+        &self,
+        binding: c_uint,
+        range: &RingRange<T>,
+    ) -> Result<(), GlError> {
+        self.gl.bind_buffer_range(
+            GL_UNIFORM_BUFFER,
+            binding,
+            range.buffer(),
+            range.offset(),
+            range.size(),
+        )
+    }
+
+    #[track_caller]
+    pub fn bind_texture(&self, unit: c_uint, texture: c_uint) -> Result<(), GlError> {
+        self.gl.bind_texture(unit, texture)
+    }
+
+    #[track_caller]
+    pub fn clear(&self, buffers: ClearBuffers) -> Result<(), GlError> {
+        let previous = self.lift_write_masks()?;
+
+        unsafe {
+            self.gl.Clear(buffers.gl_mask());
+        }
+
+        self.gl.check("glClear")?;
+        self.gl.set_pipeline(&previous)
+    }
+
+    #[track_caller]
+    pub fn clear_color_buffer(
+        &self,
+        draw_buffer: usize,
+        color: impl ClearColor,
+    ) -> Result<(), GlError> {
+        let previous = self.lift_write_masks()?;
+
+        let operation = color.clear_buffer(&self.gl, draw_buffer);
+
+        self.gl.check(operation)?;
+        self.gl.set_pipeline(&previous)
+    }
+
+    #[track_caller]
+    pub fn clear_depth_buffer(&self, depth: f32) -> Result<(), GlError> {
+        let previous = self.lift_write_masks()?;
+
+        unsafe {
+            self.gl.ClearBufferfv(GL_DEPTH, 0, &depth);
+        }
+
+        self.gl.check("glClearBufferfv")?;
+        self.gl.set_pipeline(&previous)
+    }
+
+    #[track_caller]
+    pub fn clear_depth_stencil_buffer(&self, depth: f32, stencil: c_int) -> Result<(), GlError> {
+        let previous = self.lift_write_masks()?;
+
+        unsafe {
+            self.gl.ClearBufferfi(GL_DEPTH_STENCIL, 0, depth, stencil);
+        }
+
+        self.gl.check("glClearBufferfi")?;
+        self.gl.set_pipeline(&previous)
+    }
+
+    #[track_caller]
+    pub fn clear_stencil_buffer(&self, stencil: c_int) -> Result<(), GlError> {
+        let previous = self.lift_write_masks()?;
+
         unsafe {
-            self.gl.Clear(GL_COLOR_BUFFER_BIT | GL_DEPTH_BUFFER_BIT);
+            self.gl.ClearBufferiv(GL_STENCIL, 0, &stencil);
         }
+
+        self.gl.check("glClearBufferiv")?;
+        self.gl.set_pipeline(&previous)
+    }
+
+    // Deletes the GL objects of resources dropped since the last call. Called
+    // at the start of every draw, call it explicitly when not drawing.
+    #[track_caller]
+    pub fn collect_garbage(&self) -> Result<(), GlError> {
+        self.gl.collect_garbage()
+    }
+
+    pub fn create_shader_program<'s>(
+        &self,
+        vertex_src: &'s str,
+        fragment_src: &'s str,
+    ) -> ProgramBuilder<'s> {
+        ProgramBuilder::new(self, vertex_src, fragment_src)
+    }
+
+    pub fn create_spirv_program<'s>(
+        &self,
+        vertex: SpirvShader<'s>,
+        fragment: SpirvShader<'s>,
+    ) -> ProgramBuilder<'s> {
+        ProgramBuilder::from_spirv(self, vertex, fragment)
     }
-    pub fn create_shader_program<'a>(
-        &'a self,
-        vertex_src: &'a str,
-        fragment_src: &'a str,
-    ) -> ProgramBuilder {
-        ProgramBuilder::new(&self.gl, vertex_src, fragment_src)
+
+    pub fn create_watched_program(
+        &self,
+        files: ShaderFiles,
+    ) -> Result<WatchedProgram, ProgramError> {
+        WatchedProgram::new(self, files)
+    }
+
+    #[track_caller]
+    pub fn create_draw_count_buffer(&self, count: u32) -> Result<DrawCountBuffer, GlError> {
+        DrawCountBuffer::new(&self.gl, count)
+    }
+
+    #[track_caller]
+    pub fn create_draw_indirect_buffer<C: IndirectCommand>(
+        &self,
+        commands: &[C],
+    ) -> Result<DrawIndirectBuffer<C>, GlError> {
+        DrawIndirectBuffer::new(&self.gl, commands)
     }
 
+    #[track_caller]
+    pub fn create_dynamic_indices<I: IndexType, const SIZE: usize>(
+        &self,
+        indices: &[I; SIZE],
+    ) -> Result<Indices<I, SIZE>, GlError> {
+        Indices::new(&self.gl, indices, BufferStorage::Dynamic)
+    }
+
+    #[track_caller]
+    pub fn create_dynamic_vertices<
+        T: VertexAttributeType,
+        const AMOUNT: usize,
+        const SIZE: usize,
+    >(
+        &self,
+        data: &[VertexAttribute<T, SIZE>; AMOUNT],
+    ) -> Result<Vertices<T, AMOUNT, SIZE>, GlError> {
+        Vertices::new(&self.gl, data, BufferStorage::Dynamic)
+    }
+
+    #[track_caller]
     pub fn create_indices<I: IndexType, const SIZE: usize>(
         &self,
         indices: &[I; SIZE],
-    ) -> Indices<I, SIZE> {
-        Indices::new(&self.gl, indices)
+    ) -> Result<Indices<I, SIZE>, GlError> {
+        Indices::new(&self.gl, indices, BufferStorage::Static)
+    }
+
+    #[track_caller]
+    pub fn create_mesh(&self) -> Result<Mesh, GlError> {
+        Mesh::new(&self.gl)
+    }
+
+    pub fn create_profiler(&self) -> Profiler {
+        Profiler::new(&self.gl)
+    }
+
+    #[track_caller]
+    pub fn create_query(&self, kind: QueryKind) -> Result<Query, GlError> {
+        Query::new(&self.gl, kind)
     }
 
+    #[track_caller]
+    pub fn create_ring_buffer(
+        &self,
+        frame_size: usize,
+        frames: usize,
+    ) -> Result<RingBuffer, RingBufferError> {
+        RingBuffer::new(&self.gl, frame_size, frames)
+    }
+
+    #[track_caller]
     pub fn create_vertices<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize>(
         &self,
         data: &[VertexAttribute<T, SIZE>; AMOUNT],
-    ) -> Vertices<T, AMOUNT, SIZE> {
-        Vertices::new(&self.gl, data)
+    ) -> Result<Vertices<T, AMOUNT, SIZE>, GlError> {
+        Vertices::new(&self.gl, data, BufferStorage::Static)
+    }
+
+    #[track_caller]
+    pub fn debug_group(&self, name: &str) -> Result<DebugGroup<'_>, GlError> {
+        DebugGroup::new(&self.gl, name)
+    }
+
+    // Deleted with the other dropped resources at the next draw or
+    // `collect_garbage`.
+    pub fn delete_framebuffer(&self, framebuffer: c_uint) {
+        self.gl.delete_later(GlObject::Framebuffer(framebuffer));
+    }
+
+    pub fn delete_texture(&self, texture: c_uint) {
+        self.gl.delete_later(GlObject::Texture(texture));
+    }
+
+    // Closes the frame counted by `stats` and deletes the resources dropped
+    // during it.
+    #[track_caller]
+    pub fn end_frame(&self) -> Result<(), GlError> {
+        self.last_frame.set(self.gl.take_frame_stats());
+        self.collect_garbage()
+    }
+
+    // The fence is flushed right away so polling it is guaranteed to make
+    // progress.
+    #[track_caller]
+    pub fn fence(&self) -> Result<Fence, GlError> {
+        let fence = Fence::new(&self.gl)?;
+
+        self.flush()?;
+
+        Ok(fence)
+    }
+
+    #[track_caller]
+    pub fn finish(&self) -> Result<(), GlError> {
+        unsafe {
+            self.gl.Finish();
+        }
+
+        self.gl.check("glFinish")
+    }
+
+    #[track_caller]
+    pub fn flush(&self) -> Result<(), GlError> {
+        unsafe {
+            self.gl.Flush();
+        }
+
+        self.gl.check("glFlush")
+    }
+
+    pub fn invalidate_state(&self) {
+        self.gl.invalidate();
+    }
+
+    #[track_caller]
+    pub fn is_debug_context(&self) -> Result<bool, GlError> {
+        let mut flags = 0;
+
+        unsafe {
+            self.gl.GetIntegerv(GL_CONTEXT_FLAGS, &mut flags);
+        }
+
+        self.gl.check("glGetIntegerv")?;
+
+        Ok(flags as u32 & GL_CONTEXT_FLAG_DEBUG_BIT.0 != 0)
+    }
+
+    #[track_caller]
+    pub(crate) fn new(gl: GlFns) -> Result<Self, GlError> {
+        let gl = Rc::new(Context::new(gl));
+        let mut vertex_array = 0;
+
+        unsafe {
+            gl.CreateVertexArrays(1, &mut vertex_array);
+        }
+
+        gl.check("glCreateVertexArrays")?;
+
+        let renderer = Self {
+            gl,
+            last_frame: Cell::default(),
+            vertex_array,
+        };
+
+        renderer.gl.set_pipeline(&PipelineState::default())?;
+
+        Ok(renderer)
+    }
+
+    pub fn live_resources(&self) -> Vec<Resource> {
+        self.gl.resources()
+    }
+
+    pub fn pipeline(&self) -> PipelineState {
+        self.gl.pipeline()
     }
 
-    pub fn render_vertices(&self, program: &Program) -> VertexStage {
-        program.apply();
+    #[track_caller]
+    pub fn pop_debug_group(&self) -> Result<(), GlError> {
+        self.gl.pop_debug_group()
+    }
+
+    #[track_caller]
+    pub fn push_debug_group(&self, name: &str) -> Result<(), GlError> {
+        self.gl.push_debug_group(name)
+    }
+
+    #[track_caller]
+    pub fn remove_debug_callback(&self) -> Result<(), GlError> {
+        self.gl.set_debug_callback(None)
+    }
+
+    // Like `register_texture`. The memory is counted on the attachments.
+    pub fn register_framebuffer(&self, framebuffer: c_uint) {
+        self.gl.register(ResourceKind::Framebuffer, framebuffer, 0);
+    }
+
+    // Hands a texture created outside the renderer over to it, so it is
+    // counted in `stats` and reported if it leaks. Delete it through
+    // `delete_texture`.
+    pub fn register_texture(&self, texture: c_uint, memory: usize) {
+        self.gl.register(ResourceKind::Texture, texture, memory);
+    }
+
+    pub fn remove_leak_callback(&self) {
+        self.gl.set_leak_callback(None);
+    }
+
+    // Errors from collecting garbage and binding the program are reported by
+    // the draw.
+    #[track_caller]
+    pub fn render_vertices<'a>(&'a self, program: &'a Program) -> VertexStage<'a> {
+        let mut stage = VertexStage::new(&self.gl, program);
+
+        stage.check(self.gl.collect_garbage());
+        stage.check(program.apply());
+        stage.check(self.gl.bind_vertex_array(self.vertex_array));
+
+        stage
+    }
+
+    #[track_caller]
+    pub fn render_mesh<'a>(&'a self, program: &'a Program, mesh: &'a Mesh) -> MeshStage<'a> {
+        let error = match self.gl.collect_garbage() {
+            Ok(()) => program.apply().err(),
+            Err(error) => Some(error),
+        };
+
+        MeshStage {
+            attributes: program.attributes(),
+            base_instance: 0,
+            base_vertex: 0,
+            error,
+            gl: &self.gl,
+            instances: 1,
+            mesh,
+            pipeline: None,
+            primitive: program.primitive(),
+            range: None,
+        }
+    }
+
+    #[track_caller]
+    pub fn set_clear_color(&self, color: impl Into<Vec4>) -> Result<(), GlError> {
+        let color = color.into();
+
+        unsafe {
+            self.gl.ClearColor(color.x, color.y, color.z, color.w);
+        }
+
+        self.gl.check("glClearColor")
+    }
+
+    #[track_caller]
+    pub fn set_clear_depth(&self, depth: f32) -> Result<(), GlError> {
+        unsafe {
+            self.gl.ClearDepthf(depth);
+        }
 
-        VertexStage::new(&self.gl)
+        self.gl.check("glClearDepthf")
     }
-    pub fn set_clear_color(&self) {
+
+    #[track_caller]
+    pub fn set_clear_stencil(&self, stencil: c_int) -> Result<(), GlError> {
         unsafe {
-            self.gl.ClearColor(0.0, 0.0, 0.0, 1.0);
+            self.gl.ClearStencil(stencil);
         }
+
+        self.gl.check("glClearStencil")
     }
 
-    pub fn set_viewport(&self, w: i32, h: i32) {
+    // Messages only arrive from debug contexts, see `WindowBuilder::with_debug_context`.
+    #[track_caller]
+    pub fn set_debug_callback(
+        &self,
+        callback: impl FnMut(&DebugMessage) + 'static,
+    ) -> Result<(), GlError> {
+        self.gl
+            .set_debug_callback(Some(Box::new(RefCell::new(Box::new(callback)))))
+    }
+
+    #[track_caller]
+    pub fn set_debug_messages(&self, filter: DebugFilter, enabled: bool) -> Result<(), GlError> {
+        let (source, kind, severity) = filter.gl_types();
+
         unsafe {
-            self.gl.Viewport(0, 0, w, h);
+            self.gl
+                .DebugMessageControl(source, kind, severity, 0, null(), enabled as _);
         }
+
+        self.gl.check("glDebugMessageControl")
+    }
+
+    #[track_caller]
+    pub fn set_framebuffer_label(&self, framebuffer: c_uint, label: &str) -> Result<(), GlError> {
+        self.gl
+            .object_label(ResourceKind::Framebuffer, framebuffer, label)
+    }
+
+    #[track_caller]
+    pub fn set_framebuffer_srgb(&self, enabled: bool) -> Result<(), GlError> {
+        self.gl.set_enabled(GL_FRAMEBUFFER_SRGB, enabled)
+    }
+
+    // Called when the renderer is dropped, for every resource that is still
+    // alive then. Until then, `live_resources` lists what is alive.
+    pub fn set_leak_callback(&self, callback: impl FnMut(&Resource) + 'static) {
+        self.gl.set_leak_callback(Some(Box::new(callback)));
     }
+
+    #[track_caller]
+    pub fn set_pipeline(&self, pipeline: &PipelineState) -> Result<(), GlError> {
+        self.gl.set_pipeline(pipeline)
+    }
+
+    #[track_caller]
+    pub fn set_scissor(&self, scissor: Option<Rect>) -> Result<(), GlError> {
+        self.set_pipeline(&self.pipeline().with_scissor(scissor))
+    }
+
+    #[track_caller]
+    pub fn set_texture_label(&self, texture: c_uint, label: &str) -> Result<(), GlError> {
+        self.gl.object_label(ResourceKind::Texture, texture, label)
+    }
+
+    #[track_caller]
+    pub fn set_viewport(&self, viewport: impl Into<Viewport>) -> Result<(), GlError> {
+        let Viewport {
+            depth_far,
+            depth_near,
+            rect,
+        } = viewport.into();
+
+        unsafe {
+            self.gl.Viewport(rect.x, rect.y, rect.width, rect.height);
+        }
+
+        self.gl.check("glViewport")?;
+
+        unsafe {
+            self.gl.DepthRange(depth_near, depth_far);
+        }
+
+        self.gl.check("glDepthRange")
+    }
+
+    #[track_caller]
+    pub fn set_viewports(&self, viewports: &[Viewport]) -> Result<(), GlError> {
+        for (index, viewport) in viewports.iter().enumerate() {
+            let rect = viewport.rect;
+
+            unsafe {
+                self.gl.ViewportIndexedf(
+                    index as _,
+                    rect.x as _,
+                    rect.y as _,
+                    rect.width as _,
+                    rect.height as _,
+                );
+            }
+
+            self.gl.check("glViewportIndexedf")?;
+
+            unsafe {
+                self.gl
+                    .DepthRangeIndexed(index as _, viewport.depth_near, viewport.depth_far);
+            }
+
+            self.gl.check("glDepthRangeIndexed")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn stats(&self) -> RendererStats {
+        RendererStats {
+            frame: self.last_frame.get(),
+            resources: self.gl.resource_usage(),
+        }
+    }
+
+    // Handles are resolved against the given resources. The program, mesh and
+    // pipeline stay current from one command to the next, so packets sorted
+    // next to each other can share state.
+    #[track_caller]
+    pub fn submit(
+        &self,
+        commands: &CommandBuffer,
+        programs: &[&Program],
+        meshes: &[&Mesh],
+    ) -> Result<(), SubmitError> {
+        let mut mesh = None;
+        let mut pipeline = None;
+        let mut program = None;
+
+        for command in commands.commands() {
+            match command {
+                Command::BindMesh(handle) => {
+                    let found = meshes.iter().find(|mesh| mesh.handle() == *handle);
+
+                    mesh = Some(*found.ok_or(SubmitError::UnknownMesh(*handle))?);
+                }
+                Command::Draw { instances, range } => {
+                    let program = program.ok_or(SubmitError::NoProgram)?;
+                    let mesh = mesh.ok_or(SubmitError::NoMesh)?;
+                    let mut stage = self.render_mesh(program, mesh).with_instances(*instances);
+
+                    if let Some(pipeline) = &pipeline {
+                        stage = stage.with_pipeline(pipeline);
+                    }
+
+                    if let Some((first, count)) = *range {
+                        stage = stage.with_range(first, count);
+                    }
+
+                    stage.draw()?;
+                }
+                Command::SetPipeline(state) => pipeline = Some(*state),
+                Command::SetUniform { name, value } => {
+                    program
+                        .ok_or(SubmitError::NoProgram)?
+                        .set_uniform(name, value)?;
+                }
+                Command::UseProgram(handle) => {
+                    let found = programs.iter().find(|program| program.handle() == *handle);
+
+                    program = Some(*found.ok_or(SubmitError::UnknownProgram(*handle))?);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Clears honour the write masks, so they are lifted for the duration of
+    // the clear. Returns the pipeline to restore afterwards.
+    #[track_caller]
+    fn lift_write_masks(&self) -> Result<PipelineState, GlError> {
+        let previous = self.pipeline();
+        let mut pipeline = previous;
+
+        pipeline.color_mask = [ColorMask::ALL; MAX_COLOR_ATTACHMENTS];
+        pipeline.depth.write = true;
+
+        if let Some(stencil) = &mut pipeline.stencil {
+            stencil.back.write_mask = !0;
+            stencil.front.write_mask = !0;
+        }
+
+        self.gl.set_pipeline(&pipeline)?;
+
+        Ok(previous)
+    }
+}
+
+// The context is still current here unless the window was dropped first, in
+// which case it is already lost. Resources that outlive the renderer keep the
+// context alive, so they are reported as leaks here rather than when it goes.
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        self.gl
+            .delete_later(GlObject::VertexArray(self.vertex_array));
+        self.gl.lose();
+        self.gl.report_leaks();
+    }
+}
+
+struct Binding {
+    divisor: c_uint,
+    len: usize,
+    location: c_uint,
 }
 
 pub struct VertexStage<'a> {
-    gl: &'a GlFns,
+    attributes: &'a [AttributeInfo],
+    base_instance: c_uint,
+    bound: Vec<Binding>,
+    error: Option<DrawError>,
+    gl: &'a Context,
+    instances: usize,
     layout_index: c_uint,
+    pipeline: Option<PipelineState>,
+    primitive: Primitive,
 }
 
 impl<'a> VertexStage<'a> {
+    #[track_caller]
+    pub fn add_instance_vertices<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize>(
+        self,
+        vertices: &Vertices<T, AMOUNT, SIZE>,
+        divisor: c_uint,
+    ) -> Self {
+        self.add(vertices.vertex_attrib(divisor.max(1)), AMOUNT)
+    }
+
+    #[track_caller]
+    pub fn add_ring_vertices<T: VertexAttributeType, const SIZE: usize>(
+        self,
+        range: &RingRange<VertexAttribute<T, SIZE>>,
+    ) -> Self {
+        self.add(range.vertex_attrib(0), range.len())
+    }
+
+    #[track_caller]
     pub fn add_vertices<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize>(
-        mut self,
+        self,
         vertices: &Vertices<T, AMOUNT, SIZE>,
     ) -> Self {
-        vertices.render(self.layout_index);
+        self.add(vertices.vertex_attrib(0), AMOUNT)
+    }
 
-        self.layout_index += 1;
+    #[track_caller]
+    pub fn bind_instance_vertices<
+        T: VertexAttributeType,
+        const AMOUNT: usize,
+        const SIZE: usize,
+    >(
+        self,
+        name: &str,
+        vertices: &Vertices<T, AMOUNT, SIZE>,
+        divisor: c_uint,
+    ) -> Self {
+        self.bind_named(name, vertices.vertex_attrib(divisor.max(1)), AMOUNT)
+    }
+
+    #[track_caller]
+    pub fn bind_ring_vertices<T: VertexAttributeType, const SIZE: usize>(
+        self,
+        name: &str,
+        range: &RingRange<VertexAttribute<T, SIZE>>,
+    ) -> Self {
+        self.bind_named(name, range.vertex_attrib(0), range.len())
+    }
+
+    #[track_caller]
+    pub fn bind_vertices<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize>(
+        self,
+        name: &str,
+        vertices: &Vertices<T, AMOUNT, SIZE>,
+    ) -> Self {
+        self.bind_named(name, vertices.vertex_attrib(0), AMOUNT)
+    }
+
+    #[track_caller]
+    pub fn draw_arrays(mut self, first: usize, count: usize) -> Result<(), DrawError> {
+        self.validate()?;
+        self.check_instances()?;
+
+        if let Some(len) = self
+            .bound
+            .iter()
+            .filter(|binding| binding.divisor == 0)
+            .map(|binding| binding.len)
+            .min()
+        {
+            check_range(first, count, len)?;
+        }
+
+        unsafe {
+            self.gl.DrawArraysInstancedBaseInstance(
+                self.primitive.gl_type(),
+                first as _,
+                count as _,
+                self.instances as _,
+                self.base_instance,
+            );
+        }
+
+        self.gl.check("glDrawArraysInstancedBaseInstance")?;
+        self.gl.record_draw(self.primitive, count, self.instances);
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn multi_draw_arrays_indirect(
+        mut self,
+        commands: &DrawIndirectBuffer<DrawArraysIndirectCommand>,
+    ) -> Result<(), DrawError> {
+        self.validate()?;
+        commands.bind()?;
+
+        unsafe {
+            self.gl.MultiDrawArraysIndirect(
+                self.primitive.gl_type(),
+                null(),
+                commands.len() as _,
+                0,
+            );
+        }
+
+        self.gl.check("glMultiDrawArraysIndirect")?;
+        self.gl.record_draw(self.primitive, 0, 0);
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn multi_draw_arrays_indirect_count(
+        mut self,
+        commands: &DrawIndirectBuffer<DrawArraysIndirectCommand>,
+        count: &DrawCountBuffer,
+    ) -> Result<(), DrawError> {
+        self.validate()?;
+        commands.bind()?;
+        count.bind()?;
+
+        unsafe {
+            self.gl.MultiDrawArraysIndirectCount(
+                self.primitive.gl_type(),
+                null(),
+                0,
+                commands.len() as _,
+                0,
+            );
+        }
+
+        self.gl.check("glMultiDrawArraysIndirectCount")?;
+        self.gl.record_draw(self.primitive, 0, 0);
+
+        Ok(())
+    }
+
+    pub fn with_base_instance(mut self, base_instance: c_uint) -> Self {
+        self.base_instance = base_instance;
 
         self
     }
+
+    #[track_caller]
     pub fn with_indices<I: IndexType, const SIZE: usize>(
-        self,
+        mut self,
         indices: &Indices<I, SIZE>,
     ) -> IndexStage<'a, I> {
-        indices.bind();
+        self.check(indices.bind());
 
         IndexStage {
-            gl: self.gl,
+            base_vertex: 0,
+            count: indices.len(),
+            first: 0,
             indices_len: indices.len(),
-            layout_index: self.layout_index,
+            ring_offset: None,
+            vertex_stage: self,
             _p: PhantomData,
         }
     }
 
-    fn new(gl: &'a GlFns) -> Self {
+    pub fn with_instances(mut self, instances: usize) -> Self {
+        self.instances = instances;
+
+        self
+    }
+
+    pub fn with_pipeline(mut self, pipeline: &PipelineState) -> Self {
+        self.pipeline = Some(*pipeline);
+
+        self
+    }
+
+    pub fn with_primitive(mut self, primitive: Primitive) -> Self {
+        self.primitive = primitive;
+
+        self
+    }
+
+    #[track_caller]
+    pub fn with_ring_indices<I: IndexType>(mut self, range: &RingRange<I>) -> IndexStage<'a, I> {
+        self.check(self.gl.bind_buffer(GL_ELEMENT_ARRAY_BUFFER, range.buffer()));
+
+        IndexStage {
+            base_vertex: 0,
+            count: range.len(),
+            first: 0,
+            indices_len: range.len(),
+            ring_offset: Some(range.offset()),
+            vertex_stage: self,
+            _p: PhantomData,
+        }
+    }
+
+    #[track_caller]
+    fn add(mut self, attrib: VertexAttribState, len: usize) -> Self {
+        let location = self.layout_index;
+
+        if let Some(attribute) = self
+            .attributes
+            .iter()
+            .find(|attribute| attribute.covers(location))
+        {
+            self.check(attribute.validate(attrib.component_type, attrib.components));
+        }
+
+        self.bind(location, attrib, len);
+        self.layout_index += 1;
+
+        self
+    }
+
+    #[track_caller]
+    fn bind(&mut self, location: c_uint, attrib: VertexAttribState, len: usize) {
+        self.check(self.gl.set_vertex_attrib(location, attrib));
+        self.bound.retain(|binding| binding.location != location);
+        self.bound.push(Binding {
+            divisor: attrib.divisor,
+            len,
+            location,
+        });
+    }
+
+    #[track_caller]
+    fn bind_named(mut self, name: &str, attrib: VertexAttribState, len: usize) -> Self {
+        let Some(attribute) = self
+            .attributes
+            .iter()
+            .find(|attribute| &*attribute.name == name)
+        else {
+            self.check(Err(VertexLayoutError::NoSuchAttribute(name.to_owned())));
+
+            return self;
+        };
+
+        if let Err(error) = attribute.validate_value(attrib.component_type, attrib.components) {
+            self.check(Err(error));
+
+            return self;
+        }
+
+        // Each column of a matrix takes the next location and reads its part
+        // of the same element.
+        let column_size = attrib.stride / attribute.columns;
+
+        for column in 0..attribute.columns {
+            let column_attrib = VertexAttribState {
+                components: attribute.components,
+                offset: attrib.offset + column * column_size,
+                ..attrib
+            };
+
+            self.bind(attribute.location + column as c_uint, column_attrib, len);
+        }
+
+        self
+    }
+
+    // Keeps the first error for the draw to report.
+    fn check(&mut self, result: Result<(), impl Into<DrawError>>) {
+        if let Err(error) = result {
+            self.error.get_or_insert(error.into());
+        }
+    }
+
+    fn check_instances(&self) -> Result<(), DrawError> {
+        for binding in self.bound.iter().filter(|binding| binding.divisor != 0) {
+            let fetched = self.instances.div_ceil(binding.divisor as usize);
+
+            check_range(self.base_instance as _, fetched, binding.len)?;
+        }
+
+        Ok(())
+    }
+
+    #[track_caller]
+    fn validate(&mut self) -> Result<(), DrawError> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        if let Some(pipeline) = &self.pipeline {
+            self.gl.set_pipeline(pipeline)?;
+        }
+
+        self.gl.disable_vertex_attribs(|location| {
+            self.bound
+                .iter()
+                .any(|binding| binding.location == location)
+        })?;
+
+        for attribute in self.attributes {
+            let columns = attribute.location..attribute.location + attribute.columns as c_uint;
+
+            if !columns.into_iter().all(|location| {
+                self.bound
+                    .iter()
+                    .any(|binding| binding.location == location)
+            }) {
+                return Err(VertexLayoutError::MissingBuffer(attribute.name.clone()).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn new(gl: &'a Context, program: &'a Program) -> Self {
         Self {
+            attributes: program.attributes(),
+            base_instance: 0,
+            bound: Vec::new(),
+            error: None,
             gl,
+            instances: 1,
             layout_index: 0,
+            pipeline: None,
+            primitive: program.primitive(),
         }
     }
 }
 
 pub struct IndexStage<'a, I: IndexType> {
-    gl: &'a GlFns,
+    base_vertex: c_int,
+    count: usize,
+    first: usize,
     indices_len: usize,
-    layout_index: c_uint,
+    ring_offset: Option<usize>,
+    vertex_stage: VertexStage<'a>,
     _p: PhantomData<I>,
 }
 
 impl<I: IndexType> IndexStage<'_, I> {
-    pub fn finish(self) {
+    #[track_caller]
+    pub fn finish(mut self) -> Result<(), DrawError> {
+        self.vertex_stage.validate()?;
+        self.vertex_stage.check_instances()?;
+        check_range(self.first, self.count, self.indices_len)?;
+
         unsafe {
-            self.gl
-                .DrawElements(GL_TRIANGLES, self.indices_len as _, I::GL_TYPE, null());
+            self.vertex_stage
+                .gl
+                .DrawElementsInstancedBaseVertexBaseInstance(
+                    self.vertex_stage.primitive.gl_type(),
+                    self.count as _,
+                    I::GL_TYPE,
+                    (self.ring_offset.unwrap_or(0) + self.first * std::mem::size_of::<I>()) as _,
+                    self.vertex_stage.instances as _,
+                    self.base_vertex,
+                    self.vertex_stage.base_instance,
+                );
+        }
+
+        self.vertex_stage
+            .gl
+            .check("glDrawElementsInstancedBaseVertexBaseInstance")?;
+        self.vertex_stage.gl.record_draw(
+            self.vertex_stage.primitive,
+            self.count,
+            self.vertex_stage.instances,
+        );
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn multi_draw_indirect(
+        mut self,
+        commands: &DrawIndirectBuffer<DrawElementsIndirectCommand>,
+    ) -> Result<(), DrawError> {
+        self.check_indirect()?;
+        self.vertex_stage.validate()?;
+        commands.bind()?;
+
+        unsafe {
+            self.vertex_stage.gl.MultiDrawElementsIndirect(
+                self.vertex_stage.primitive.gl_type(),
+                I::GL_TYPE,
+                null(),
+                commands.len() as _,
+                0,
+            );
+        }
+
+        self.vertex_stage.gl.check("glMultiDrawElementsIndirect")?;
+        self.vertex_stage
+            .gl
+            .record_draw(self.vertex_stage.primitive, 0, 0);
 
-            for i in 0..self.layout_index {
-                self.gl.DisableVertexAttribArray(i);
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn multi_draw_indirect_count(
+        mut self,
+        commands: &DrawIndirectBuffer<DrawElementsIndirectCommand>,
+        count: &DrawCountBuffer,
+    ) -> Result<(), DrawError> {
+        self.check_indirect()?;
+        self.vertex_stage.validate()?;
+        commands.bind()?;
+        count.bind()?;
+
+        unsafe {
+            self.vertex_stage.gl.MultiDrawElementsIndirectCount(
+                self.vertex_stage.primitive.gl_type(),
+                I::GL_TYPE,
+                null(),
+                0,
+                commands.len() as _,
+                0,
+            );
+        }
+
+        self.vertex_stage
+            .gl
+            .check("glMultiDrawElementsIndirectCount")?;
+        self.vertex_stage
+            .gl
+            .record_draw(self.vertex_stage.primitive, 0, 0);
+
+        Ok(())
+    }
+
+    pub fn with_base_vertex(mut self, base_vertex: c_int) -> Self {
+        self.base_vertex = base_vertex;
+
+        self
+    }
+
+    pub fn with_range(mut self, first: usize, count: usize) -> Self {
+        self.first = first;
+        self.count = count;
+
+        self
+    }
+
+    // The commands carry their own first index and base vertex, counted from
+    // the start of the index buffer. A ring slice starts elsewhere, so it is
+    // rejected along with ranges and base vertices that would be ignored.
+    fn check_indirect(&self) -> Result<(), DrawError> {
+        if self.ring_offset.is_some()
+            || self.base_vertex != 0
+            || (self.first, self.count) != (0, self.indices_len)
+        {
+            return Err(DrawError::IndirectIndexRange);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct MeshStage<'a> {
+    attributes: &'a [AttributeInfo],
+    base_instance: c_uint,
+    base_vertex: c_int,
+    error: Option<GlError>,
+    gl: &'a Context,
+    instances: usize,
+    mesh: &'a Mesh,
+    pipeline: Option<PipelineState>,
+    primitive: Primitive,
+    range: Option<(usize, usize)>,
+}
+
+impl MeshStage<'_> {
+    #[track_caller]
+    pub fn draw(self) -> Result<(), DrawError> {
+        if let Some(error) = self.error {
+            return Err(error.into());
+        }
+
+        self.mesh.validate(self.attributes)?;
+
+        for attribute in self
+            .mesh
+            .attributes()
+            .iter()
+            .filter(|attribute| attribute.divisor != 0)
+        {
+            let fetched = self.instances.div_ceil(attribute.divisor as usize);
+
+            check_range(self.base_instance as _, fetched, attribute.len)?;
+        }
+
+        let len = match self.mesh.indices() {
+            Some(indices) => indices.len,
+            None => self
+                .mesh
+                .attributes()
+                .iter()
+                .filter(|attribute| attribute.divisor == 0)
+                .map(|attribute| attribute.len)
+                .min()
+                .unwrap_or(0),
+        };
+        let (first, count) = self.range.unwrap_or((0, len));
+
+        check_range(first, count, len)?;
+
+        if let Some(pipeline) = &self.pipeline {
+            self.gl.set_pipeline(pipeline)?;
+        }
+
+        self.mesh.bind()?;
+
+        match self.mesh.indices() {
+            Some(indices) => {
+                unsafe {
+                    self.gl.DrawElementsInstancedBaseVertexBaseInstance(
+                        self.primitive.gl_type(),
+                        count as _,
+                        indices.gl_type,
+                        (first * indices.size) as _,
+                        self.instances as _,
+                        self.base_vertex,
+                        self.base_instance,
+                    );
+                }
+
+                self.gl
+                    .check("glDrawElementsInstancedBaseVertexBaseInstance")?;
+            }
+            None => {
+                unsafe {
+                    self.gl.DrawArraysInstancedBaseInstance(
+                        self.primitive.gl_type(),
+                        first as _,
+                        count as _,
+                        self.instances as _,
+                        self.base_instance,
+                    );
+                }
+
+                self.gl.check("glDrawArraysInstancedBaseInstance")?;
             }
         }
+
+        self.gl.record_draw(self.primitive, count, self.instances);
+
+        Ok(())
+    }
+
+    pub fn with_base_instance(mut self, base_instance: c_uint) -> Self {
+        self.base_instance = base_instance;
+
+        self
+    }
+
+    pub fn with_base_vertex(mut self, base_vertex: c_int) -> Self {
+        self.base_vertex = base_vertex;
+
+        self
+    }
+
+    pub fn with_instances(mut self, instances: usize) -> Self {
+        self.instances = instances;
+
+        self
+    }
+
+    pub fn with_pipeline(mut self, pipeline: &PipelineState) -> Self {
+        self.pipeline = Some(*pipeline);
+
+        self
+    }
+
+    pub fn with_primitive(mut self, primitive: Primitive) -> Self {
+        self.primitive = primitive;
+
+        self
+    }
+
+    pub fn with_range(mut self, first: usize, count: usize) -> Self {
+        self.range = Some((first, count));
+
+        self
+    }
+}
+
+fn check_range(first: usize, count: usize, len: usize) -> Result<(), DrawError> {
+    if first.checked_add(count).is_none_or(|end| end > len) {
+        return Err(DrawError::RangeOutOfBounds { count, first, len });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mock_gl, ResourceKind, VertexAttribute};
+    use gl46::{GL_FLOAT, GL_FLOAT_MAT4, GL_FLOAT_VEC3};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[cfg(all(feature = "checked-gl", debug_assertions))]
+    #[test]
+    fn attributes_errors_to_the_failing_call() {
+        use crate::{DrawError, Rect};
+
+        let renderer = mock_gl::renderer();
+
+        mock_gl::fail("glDepthRange");
+
+        let error = renderer.set_viewport(Rect::default()).unwrap_err();
+
+        assert_eq!(error.operation, "glDepthRange");
+        assert_eq!(error.location.file(), file!());
+
+        let program = renderer.create_shader_program("", "").build().unwrap();
+
+        mock_gl::fail("glUseProgram");
+
+        let Err(DrawError::Gl(error)) = renderer.render_vertices(&program).draw_arrays(0, 0) else {
+            panic!("expected the draw to report the failed glUseProgram");
+        };
+
+        assert_eq!(error.operation, "glUseProgram");
+        assert_eq!(error.location.file(), file!());
+    }
+
+    #[test]
+    fn binds_matrix_columns_by_name() {
+        let renderer = mock_gl::renderer();
+
+        mock_gl::set_attributes(&[("position", GL_FLOAT_VEC3, 0), ("model", GL_FLOAT_MAT4, 1)]);
+
+        let program = renderer.create_shader_program("", "").build().unwrap();
+        let positions = renderer
+            .create_vertices(&[
+                VertexAttribute::new([0.0; 3]),
+                VertexAttribute::new([1.0; 3]),
+                VertexAttribute::new([2.0; 3]),
+            ])
+            .unwrap();
+        let models = renderer
+            .create_vertices(&[VertexAttribute::new([0.0; 16])])
+            .unwrap();
+
+        renderer
+            .render_vertices(&program)
+            .bind_vertices("position", &positions)
+            .bind_instance_vertices("model", &models, 1)
+            .draw_arrays(0, 3)
+            .unwrap();
+
+        let float = GL_FLOAT.0 as i64;
+
+        assert_eq!(
+            mock_gl::calls("glVertexAttribPointer"),
+            [
+                [0, 3, float, 0, 12, 0],
+                [1, 4, float, 0, 64, 0],
+                [2, 4, float, 0, 64, 16],
+                [3, 4, float, 0, 64, 32],
+                [4, 4, float, 0, 64, 48],
+            ]
+        );
+    }
+
+    #[cfg(all(feature = "checked-gl", debug_assertions))]
+    #[test]
+    fn counts_only_successful_draws() {
+        let renderer = mock_gl::renderer();
+        let program = renderer.create_shader_program("", "").build().unwrap();
+        let vertices = renderer
+            .create_vertices(&[VertexAttribute::new([0.0; 3])])
+            .unwrap();
+        let mesh = renderer
+            .create_mesh()
+            .unwrap()
+            .with_vertices(0, &vertices)
+            .unwrap();
+
+        mock_gl::fail("glDrawArraysInstancedBaseInstance");
+
+        assert!(renderer.render_mesh(&program, &mesh).draw().is_err());
+
+        renderer.render_mesh(&program, &mesh).draw().unwrap();
+        renderer.end_frame().unwrap();
+
+        assert_eq!(renderer.stats().frame.draw_calls, 1);
+    }
+
+    #[test]
+    fn deletes_nothing_after_the_context_is_lost() {
+        let renderer = mock_gl::renderer();
+        let vertices = renderer
+            .create_vertices(&[VertexAttribute::new([0.0; 3])])
+            .unwrap();
+
+        // What the window does before destroying the GL context.
+        renderer.gl.lose();
+        drop(renderer);
+        drop(vertices);
+
+        assert!(mock_gl::calls("glDeleteBuffers").is_empty());
+        assert!(mock_gl::calls("glDeleteVertexArrays").is_empty());
+    }
+
+    #[test]
+    fn deletes_nothing_after_the_renderer_is_dropped() {
+        let renderer = mock_gl::renderer();
+        let vertices = renderer
+            .create_vertices(&[VertexAttribute::new([0.0; 3])])
+            .unwrap();
+
+        drop(renderer);
+
+        assert_eq!(mock_gl::calls("glDeleteVertexArrays").len(), 1);
+
+        drop(vertices);
+
+        assert!(mock_gl::calls("glDeleteBuffers").is_empty());
+    }
+
+    #[test]
+    fn reports_resources_that_outlive_it() {
+        let renderer = mock_gl::renderer();
+        let leaks = Rc::new(RefCell::new(Vec::new()));
+        let vertices = renderer
+            .create_vertices(&[VertexAttribute::new([0.0; 3])])
+            .unwrap();
+
+        renderer.register_texture(7, 1024);
+        renderer.set_texture_label(7, "albedo").unwrap();
+        renderer.set_leak_callback({
+            let leaks = leaks.clone();
+
+            move |resource| leaks.borrow_mut().push(resource.clone())
+        });
+        drop(renderer);
+
+        let leaks = leaks.borrow();
+
+        assert_eq!(leaks.len(), 2);
+        assert_eq!(leaks[0].kind, ResourceKind::Buffer);
+        assert_eq!(leaks[0].memory, 12);
+        assert_eq!(leaks[1].kind, ResourceKind::Texture);
+        assert_eq!(leaks[1].label.as_deref(), Some("albedo"));
+
+        drop(vertices);
     }
 }
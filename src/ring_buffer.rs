@@ -0,0 +1,203 @@
+use crate::{
+    buffer::{Buffer, BufferStorage},
+    context::{Context, VertexAttribState},
+    fence::{Fence, FenceError},
+    gl_error::GlError,
+    vertices::{VertexAttribute, VertexAttributeType},
+};
+use gl46::GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT;
+use std::{ffi::c_uint, marker::PhantomData, ptr::NonNull, rc::Rc, time::Duration};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RingBufferError {
+    #[error("Ring buffer frames must hold at least one byte")]
+    EmptyFrame,
+    #[error(transparent)]
+    Fence(#[from] FenceError),
+    #[error(transparent)]
+    Gl(#[from] GlError),
+    #[error("Failed to map a ring buffer of {0} bytes")]
+    MapFailed(usize),
+    #[error("Allocation of {size} bytes exceeds the {available} bytes left in this frame")]
+    OutOfSpace { available: usize, size: usize },
+}
+
+// A range is only valid until the ring buffer moves past its frame.
+pub struct RingRange<T> {
+    buffer: c_uint,
+    len: usize,
+    offset: usize,
+    _p: PhantomData<T>,
+}
+
+impl<T> RingRange<T> {
+    pub(crate) fn buffer(&self) -> c_uint {
+        self.buffer
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.len * std::mem::size_of::<T>()
+    }
+}
+
+impl<T: VertexAttributeType, const SIZE: usize> RingRange<VertexAttribute<T, SIZE>> {
+    pub(crate) fn vertex_attrib(&self, divisor: c_uint) -> VertexAttribState {
+        VertexAttribState {
+            buffer: self.buffer,
+            component_type: T::COMPONENT_TYPE,
+            components: SIZE,
+            divisor,
+            gl_type: T::GL_TYPE,
+            offset: self.offset,
+            stride: std::mem::size_of::<[T; SIZE]>(),
+        }
+    }
+}
+
+impl<T> Clone for RingRange<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RingRange<T> {}
+
+pub struct RingBuffer {
+    buffer: Buffer<u8>,
+    fences: Vec<Option<Fence>>,
+    frame: usize,
+    frame_size: usize,
+    gl: Rc<Context>,
+    offset: usize,
+    ptr: NonNull<u8>,
+    uniform_alignment: usize,
+}
+
+impl RingBuffer {
+    pub fn allocate<T: Copy>(&mut self, data: &[T]) -> Result<RingRange<T>, RingBufferError> {
+        self.allocate_aligned(data, std::mem::align_of::<T>())
+    }
+
+    pub fn allocate_uniform<T: Copy>(
+        &mut self,
+        value: &T,
+    ) -> Result<RingRange<T>, RingBufferError> {
+        let alignment = self.uniform_alignment.max(std::mem::align_of::<T>());
+
+        self.allocate_aligned(std::slice::from_ref(value), alignment)
+    }
+
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    pub fn frames(&self) -> usize {
+        self.fences.len()
+    }
+
+    #[track_caller]
+    pub(crate) fn new(
+        gl: &Rc<Context>,
+        frame_size: usize,
+        frames: usize,
+    ) -> Result<Self, RingBufferError> {
+        if frame_size == 0 {
+            return Err(RingBufferError::EmptyFrame);
+        }
+
+        let frames = frames.max(1);
+        let size = frame_size * frames;
+        let buffer = Buffer::uninitialized(gl, size, BufferStorage::PersistentWrite)?;
+        let ptr = buffer.map_persistent();
+
+        gl.check("glMapNamedBufferRange")?;
+
+        let ptr = NonNull::new(ptr).ok_or(RingBufferError::MapFailed(size))?;
+        let mut uniform_alignment = 0;
+
+        unsafe {
+            gl.GetIntegerv(GL_UNIFORM_BUFFER_OFFSET_ALIGNMENT, &mut uniform_alignment);
+        }
+
+        gl.check("glGetIntegerv")?;
+
+        Ok(Self {
+            buffer,
+            fences: (0..frames).map(|_| None).collect(),
+            frame: 0,
+            frame_size,
+            gl: gl.clone(),
+            offset: 0,
+            ptr,
+            uniform_alignment: uniform_alignment.max(1) as usize,
+        })
+    }
+
+    // Fences the frame that was just recorded and waits until the GPU is done
+    // with the region that gets reused next. If the wait fails the ring stays
+    // on the current frame, so nothing is written to memory still in use.
+    #[track_caller]
+    pub fn next_frame(&mut self) -> Result<(), RingBufferError> {
+        let next = (self.frame + 1) % self.fences.len();
+
+        self.fences[self.frame] = Some(Fence::new(&self.gl)?);
+
+        if let Some(fence) = &self.fences[next] {
+            while !fence.wait(Duration::MAX)? {}
+        }
+
+        self.fences[next] = None;
+        self.frame = next;
+        self.offset = 0;
+
+        Ok(())
+    }
+
+    fn allocate_aligned<T: Copy>(
+        &mut self,
+        data: &[T],
+        alignment: usize,
+    ) -> Result<RingRange<T>, RingBufferError> {
+        let size = std::mem::size_of_val(data);
+        let frame_start = self.frame * self.frame_size;
+        let start = (frame_start + self.offset).next_multiple_of(alignment) - frame_start;
+
+        if start + size > self.frame_size {
+            return Err(RingBufferError::OutOfSpace {
+                available: self.frame_size.saturating_sub(start),
+                size,
+            });
+        }
+
+        let offset = frame_start + start;
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                data.as_ptr().cast::<u8>(),
+                self.ptr.as_ptr().add(offset),
+                size,
+            );
+        }
+
+        self.offset = start + size;
+
+        Ok(RingRange {
+            buffer: self.buffer.id(),
+            len: data.len(),
+            offset,
+            _p: PhantomData,
+        })
+    }
+}
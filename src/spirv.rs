@@ -0,0 +1,61 @@
+use std::ffi::{c_uint, CString};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpirvError {
+    #[error("Entry point {0:?} contains a NUL byte")]
+    InvalidEntryPoint(String),
+}
+
+pub struct SpirvShader<'s> {
+    binary: &'s [u8],
+    constants: Vec<(c_uint, c_uint)>,
+    entry_point: CString,
+}
+
+impl<'s> SpirvShader<'s> {
+    pub(crate) fn binary(&self) -> &[u8] {
+        self.binary
+    }
+
+    pub(crate) fn constants(&self) -> &[(c_uint, c_uint)] {
+        &self.constants
+    }
+
+    pub(crate) fn entry_point(&self) -> &CString {
+        &self.entry_point
+    }
+
+    pub fn new(binary: &'s [u8]) -> Self {
+        Self {
+            binary,
+            constants: Vec::new(),
+            entry_point: c"main".to_owned(),
+        }
+    }
+
+    pub fn with_bool_constant(self, constant_id: c_uint, value: bool) -> Self {
+        self.with_constant(constant_id, value as c_uint)
+    }
+
+    pub fn with_constant(mut self, constant_id: c_uint, value: c_uint) -> Self {
+        self.constants.retain(|(id, _)| *id != constant_id);
+        self.constants.push((constant_id, value));
+
+        self
+    }
+
+    pub fn with_entry_point(mut self, entry_point: &str) -> Result<Self, SpirvError> {
+        self.entry_point = CString::new(entry_point)
+            .map_err(|_| SpirvError::InvalidEntryPoint(entry_point.to_owned()))?;
+
+        Ok(self)
+    }
+
+    pub fn with_float_constant(self, constant_id: c_uint, value: f32) -> Self {
+        self.with_constant(constant_id, value.to_bits())
+    }
+
+    pub fn with_int_constant(self, constant_id: c_uint, value: i32) -> Self {
+        self.with_constant(constant_id, value as c_uint)
+    }
+}
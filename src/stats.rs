@@ -0,0 +1,130 @@
+use gl46::{
+    ObjectIdentifier, GL_BUFFER, GL_FRAMEBUFFER, GL_PROGRAM, GL_QUERY, GL_TEXTURE, GL_VERTEX_ARRAY,
+};
+use std::{collections::HashMap, ffi::c_uint, fmt};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, strum::Display)]
+pub enum ResourceKind {
+    #[strum(serialize = "buffer")]
+    Buffer,
+    #[strum(serialize = "framebuffer")]
+    Framebuffer,
+    #[strum(serialize = "program")]
+    Program,
+    #[strum(serialize = "query")]
+    Query,
+    #[strum(serialize = "texture")]
+    Texture,
+    #[strum(serialize = "vertex array")]
+    VertexArray,
+}
+
+impl ResourceKind {
+    pub(crate) fn gl_type(self) -> ObjectIdentifier {
+        match self {
+            Self::Buffer => GL_BUFFER,
+            Self::Framebuffer => GL_FRAMEBUFFER,
+            Self::Program => GL_PROGRAM,
+            Self::Query => GL_QUERY,
+            Self::Texture => GL_TEXTURE,
+            Self::VertexArray => GL_VERTEX_ARRAY,
+        }
+    }
+}
+
+// Memory is an estimate in bytes of the storage the resource requested.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resource {
+    pub id: c_uint,
+    pub kind: ResourceKind,
+    pub label: Option<String>,
+    pub memory: usize,
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.id)?;
+
+        if let Some(label) = &self.label {
+            write!(f, " {label:?}")?;
+        }
+
+        write!(f, " ({} bytes)", self.memory)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceUsage {
+    pub count: usize,
+    pub memory: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    pub draw_calls: usize,
+    pub state_changes: usize,
+    pub triangles: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RendererStats {
+    pub frame: FrameStats,
+    pub resources: HashMap<ResourceKind, ResourceUsage>,
+}
+
+impl RendererStats {
+    pub fn resource(&self, kind: ResourceKind) -> ResourceUsage {
+        self.resources.get(&kind).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Registry {
+    resources: HashMap<(ResourceKind, c_uint), (Option<String>, usize)>,
+}
+
+impl Registry {
+    pub(crate) fn insert(&mut self, kind: ResourceKind, id: c_uint, memory: usize) {
+        self.resources.insert((kind, id), (None, memory));
+    }
+
+    pub(crate) fn remove(&mut self, kind: ResourceKind, id: c_uint) {
+        self.resources.remove(&(kind, id));
+    }
+
+    pub(crate) fn resources(&self) -> Vec<Resource> {
+        let mut resources = self
+            .resources
+            .iter()
+            .map(|(&(kind, id), (label, memory))| Resource {
+                id,
+                kind,
+                label: label.clone(),
+                memory: *memory,
+            })
+            .collect::<Vec<_>>();
+
+        resources.sort_by_key(|resource| (resource.kind, resource.id));
+
+        resources
+    }
+
+    pub(crate) fn set_label(&mut self, kind: ResourceKind, id: c_uint, label: &str) {
+        if let Some((current, _)) = self.resources.get_mut(&(kind, id)) {
+            *current = Some(label.to_owned());
+        }
+    }
+
+    pub(crate) fn usage(&self) -> HashMap<ResourceKind, ResourceUsage> {
+        let mut usage = HashMap::<_, ResourceUsage>::new();
+
+        for (&(kind, _), (_, memory)) in &self.resources {
+            let entry = usage.entry(kind).or_default();
+
+            entry.count += 1;
+            entry.memory += memory;
+        }
+
+        usage
+    }
+}
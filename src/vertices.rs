@@ -1,5 +1,11 @@
-use gl46::{GlFns, VertexAttribPointerType, GL_ARRAY_BUFFER, GL_FALSE, GL_FLOAT, GL_STATIC_DRAW};
-use std::{ffi::c_uint, marker::PhantomData, ptr::null};
+use crate::{
+    buffer::{Buffer, BufferError, BufferObject, BufferStorage},
+    context::{Context, VertexAttribState},
+    gl_error::GlError,
+    reflection::ComponentType,
+};
+use gl46::{VertexAttribPointerType, GL_FLOAT};
+use std::{ffi::c_uint, rc::Rc};
 
 #[repr(transparent)]
 pub struct VertexAttribute<T: VertexAttributeType, const SIZE: usize>([T; SIZE]);
@@ -10,72 +16,55 @@ impl<T: VertexAttributeType, const SIZE: usize> VertexAttribute<T, SIZE> {
     }
 }
 
-pub struct Vertices<'a, T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize> {
-    buffer: c_uint,
-    gl: &'a GlFns,
-    vao: c_uint,
-    _p: PhantomData<T>,
+pub struct Vertices<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize> {
+    buffer: Buffer<VertexAttribute<T, SIZE>>,
 }
 
-impl<'a, T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize>
-    Vertices<'a, T, AMOUNT, SIZE>
-{
-    pub(crate) fn render(&self, layout_index: c_uint) {
-        unsafe {
-            self.gl.EnableVertexAttribArray(layout_index);
-            self.gl.BindBuffer(GL_ARRAY_BUFFER, self.buffer);
-            self.gl.VertexAttribPointer(
-                layout_index,
-                SIZE as _,
-                T::GL_TYPE,
-                GL_FALSE.0 as _,
-                0,
-                null(),
-            );
-        }
+impl<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize> Vertices<T, AMOUNT, SIZE> {
+    pub(crate) fn buffer(&self) -> Rc<BufferObject> {
+        self.buffer.object()
     }
-    pub(crate) fn new(gl: &'a GlFns, vertex_data: &[VertexAttribute<T, SIZE>; AMOUNT]) -> Self {
-        let mut vao = 0;
-
-        unsafe {
-            gl.GenVertexArrays(1, &mut vao);
-            gl.BindVertexArray(vao);
-        }
 
-        let mut buffer = 0;
+    #[track_caller]
+    pub(crate) fn new(
+        gl: &Rc<Context>,
+        vertex_data: &[VertexAttribute<T, SIZE>; AMOUNT],
+        storage: BufferStorage,
+    ) -> Result<Self, GlError> {
+        Ok(Self {
+            buffer: Buffer::new(gl, vertex_data, storage)?,
+        })
+    }
 
-        unsafe {
-            gl.GenBuffers(1, &mut buffer);
-            gl.BindBuffer(GL_ARRAY_BUFFER, buffer);
-            gl.BufferData(
-                GL_ARRAY_BUFFER,
-                std::mem::size_of::<[VertexAttribute<T, SIZE>; AMOUNT]>() as _,
-                vertex_data.as_ptr() as _,
-                GL_STATIC_DRAW,
-            );
-        }
+    #[track_caller]
+    pub fn set_label(&self, label: &str) -> Result<(), GlError> {
+        self.buffer.set_label(label)
+    }
 
-        Self {
-            buffer,
-            gl,
-            vao,
-            _p: PhantomData,
-        }
+    #[track_caller]
+    pub fn update(
+        &self,
+        offset: usize,
+        vertex_data: &[VertexAttribute<T, SIZE>],
+    ) -> Result<(), BufferError> {
+        self.buffer.update(offset, vertex_data)
     }
-}
 
-impl<T: VertexAttributeType, const AMOUNT: usize, const SIZE: usize> Drop
-    for Vertices<'_, T, AMOUNT, SIZE>
-{
-    fn drop(&mut self) {
-        unsafe {
-            self.gl.DeleteBuffers(1, &self.buffer);
-            self.gl.DeleteVertexArrays(1, &self.vao);
+    pub(crate) fn vertex_attrib(&self, divisor: c_uint) -> VertexAttribState {
+        VertexAttribState {
+            buffer: self.buffer.id(),
+            component_type: T::COMPONENT_TYPE,
+            components: SIZE,
+            divisor,
+            gl_type: T::GL_TYPE,
+            offset: 0,
+            stride: std::mem::size_of::<[T; SIZE]>(),
         }
     }
 }
 
 pub trait VertexAttributeType {
+    const COMPONENT_TYPE: ComponentType = ComponentType::Float;
     const GL_TYPE: VertexAttribPointerType;
 }
 
@@ -0,0 +1,227 @@
+use std::ffi::c_int;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+}
+
+impl Rect {
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    pub const fn from_size(width: c_int, height: c_int) -> Self {
+        Self::new(0, 0, width, height)
+    }
+
+    pub const fn new(x: c_int, y: c_int, width: c_int, height: c_int) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub depth_far: f64,
+    pub depth_near: f64,
+    pub rect: Rect,
+}
+
+impl Viewport {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            depth_far: 1.0,
+            depth_near: 0.0,
+            rect,
+        }
+    }
+
+    pub fn with_depth_range(mut self, near: f64, far: f64) -> Self {
+        self.depth_near = near;
+        self.depth_far = far;
+
+        self
+    }
+}
+
+impl From<Rect> for Viewport {
+    fn from(rect: Rect) -> Self {
+        Self::new(rect)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Corner {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    #[default]
+    TopRight,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ViewportLayout {
+    #[default]
+    Single,
+    Columns(usize),
+    Rows(usize),
+    Grid {
+        columns: usize,
+        rows: usize,
+    },
+    PictureInPicture {
+        corner: Corner,
+        margin: c_int,
+        scale: f32,
+    },
+}
+
+impl ViewportLayout {
+    // Rects are returned in reading order, starting at the top left. For
+    // picture-in-picture the full-size view comes first, the inset second.
+    pub fn rects(&self, width: c_int, height: c_int) -> Vec<Rect> {
+        match *self {
+            Self::Single => vec![Rect::from_size(width, height)],
+            Self::Columns(columns) => grid(width, height, columns, 1),
+            Self::Rows(rows) => grid(width, height, 1, rows),
+            Self::Grid { columns, rows } => grid(width, height, columns, rows),
+            Self::PictureInPicture {
+                corner,
+                margin,
+                scale,
+            } => {
+                // The inset never grows past the full view, and the margin
+                // gives way rather than pushing it off screen.
+                let scale = scale.clamp(0.0, 1.0);
+                let inset_width = (width as f32 * scale) as c_int;
+                let inset_height = (height as f32 * scale) as c_int;
+                let (x, y) = match corner {
+                    Corner::BottomLeft => (margin, margin),
+                    Corner::BottomRight => (width - inset_width - margin, margin),
+                    Corner::TopLeft => (margin, height - inset_height - margin),
+                    Corner::TopRight => {
+                        (width - inset_width - margin, height - inset_height - margin)
+                    }
+                };
+                let x = x.clamp(0, (width - inset_width).max(0));
+                let y = y.clamp(0, (height - inset_height).max(0));
+
+                vec![
+                    Rect::from_size(width, height),
+                    Rect::new(x, y, inset_width, inset_height),
+                ]
+            }
+        }
+    }
+
+    pub fn viewports(&self, width: c_int, height: c_int) -> Vec<Viewport> {
+        self.rects(width, height)
+            .into_iter()
+            .map(Viewport::new)
+            .collect()
+    }
+}
+
+// GL puts the origin at the bottom left, so rows are laid out top-down from
+// the upper edge. Edges are computed per cell so the rects tile exactly.
+fn grid(width: c_int, height: c_int, columns: usize, rows: usize) -> Vec<Rect> {
+    let columns = columns.max(1) as c_int;
+    let rows = rows.max(1) as c_int;
+    let edge = |size: c_int, cells: c_int, i: c_int| size * i / cells;
+
+    (0..rows)
+        .flat_map(|row| {
+            (0..columns).map(move |column| {
+                let x = edge(width, columns, column);
+                let top = height - edge(height, rows, row);
+                let bottom = height - edge(height, rows, row + 1);
+
+                Rect::new(
+                    x,
+                    bottom,
+                    edge(width, columns, column + 1) - x,
+                    top - bottom,
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_unevenly() {
+        assert_eq!(
+            ViewportLayout::Columns(3).rects(100, 50),
+            [
+                Rect::new(0, 0, 33, 50),
+                Rect::new(33, 0, 33, 50),
+                Rect::new(66, 0, 34, 50),
+            ]
+        );
+        assert_eq!(
+            ViewportLayout::Rows(3).rects(50, 100),
+            [
+                Rect::new(0, 67, 50, 33),
+                Rect::new(0, 34, 50, 33),
+                Rect::new(0, 0, 50, 34),
+            ]
+        );
+
+        let rects = ViewportLayout::Grid {
+            columns: 3,
+            rows: 2,
+        }
+        .rects(101, 51);
+
+        assert_eq!(rects.len(), 6);
+        assert_eq!(
+            rects
+                .iter()
+                .map(|rect| rect.width * rect.height)
+                .sum::<c_int>(),
+            101 * 51
+        );
+    }
+
+    #[test]
+    fn treats_zero_cells_as_one() {
+        for layout in [
+            ViewportLayout::Columns(0),
+            ViewportLayout::Rows(0),
+            ViewportLayout::Grid {
+                columns: 0,
+                rows: 0,
+            },
+        ] {
+            assert_eq!(layout.rects(640, 480), [Rect::from_size(640, 480)]);
+        }
+    }
+
+    #[test]
+    fn places_picture_in_picture() {
+        let inset = |corner, scale| {
+            ViewportLayout::PictureInPicture {
+                corner,
+                margin: 10,
+                scale,
+            }
+            .rects(400, 200)[1]
+        };
+
+        assert_eq!(inset(Corner::TopRight, 0.25), Rect::new(290, 140, 100, 50));
+        assert_eq!(inset(Corner::BottomLeft, 0.25), Rect::new(10, 10, 100, 50));
+        assert_eq!(inset(Corner::TopLeft, 2.0), Rect::new(0, 0, 400, 200));
+        assert_eq!(inset(Corner::BottomRight, -1.0), Rect::new(390, 10, 0, 0));
+        assert_eq!(inset(Corner::TopRight, 0.99), Rect::new(0, 0, 396, 198));
+    }
+}
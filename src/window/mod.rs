@@ -1,13 +1,12 @@
 mod event;
 
-use crate::Renderer;
+use crate::{context::Context, GlError, Renderer};
 use fermium::{error::*, prelude::*};
 use gl46::GlFns;
-use gl46::GL_CULL_FACE;
-use gl46::GL_DEPTH_TEST;
-use gl46::GL_LESS;
+use std::cell::RefCell;
 use std::ffi::CString;
 use std::ptr::NonNull;
+use std::rc::{Rc, Weak};
 
 pub use event::Event;
 pub use event::Key;
@@ -22,15 +21,28 @@ pub enum WindowError {
     OpenGLContext(String),
     #[error("Failed to create window: {0}")]
     WindowCreation(String),
+    #[error("Failed to load OpenGL function {0}")]
+    FunctionLoading(&'static str),
+    #[error(transparent)]
+    Gl(#[from] GlError),
 }
 
+// Renderers can outlive their window, so the window keeps track of their
+// contexts to lose them while the GL context is still current.
 pub struct Window {
+    contexts: RefCell<Vec<Weak<Context>>>,
     gl_ctx: NonNull<c_void>,
     window: NonNull<SDL_Window>,
 }
 
 impl Drop for Window {
     fn drop(&mut self) {
+        for context in self.contexts.get_mut() {
+            if let Some(context) = context.upgrade() {
+                context.lose();
+            }
+        }
+
         unsafe {
             SDL_GL_DeleteContext(SDL_GLContext(self.gl_ctx.as_ptr()));
             SDL_DestroyWindow(self.window.as_ptr());
@@ -39,26 +51,63 @@ impl Drop for Window {
     }
 }
 
-impl Window {
-    pub fn create_renderer(&self) -> Result<Renderer, &'static str> {
-        let renderer = Renderer {
-            gl: unsafe { GlFns::load_from(&|proc| SDL_GL_GetProcAddress(proc.cast()))? },
-        };
+pub struct WindowBuilder {
+    debug_context: bool,
+    height: i32,
+    title: String,
+    width: i32,
+}
 
-        unsafe {
-            renderer.gl.Enable(GL_CULL_FACE);
-            renderer.gl.Enable(GL_DEPTH_TEST);
-            renderer.gl.DepthFunc(GL_LESS);
+impl WindowBuilder {
+    pub fn build(self) -> Result<Window, WindowError> {
+        Window::create(&self)
+    }
+
+    pub fn new(title: &str, width: i32, height: i32) -> Self {
+        Self {
+            debug_context: false,
+            height,
+            title: title.to_owned(),
+            width,
         }
+    }
+
+    // Debug contexts report errors and warnings through
+    // `Renderer::set_debug_callback`, at some cost in driver overhead.
+    pub fn with_debug_context(mut self, debug_context: bool) -> Self {
+        self.debug_context = debug_context;
+
+        self
+    }
+}
+
+impl Window {
+    pub fn create_renderer(&self) -> Result<Renderer, WindowError> {
+        let gl = unsafe { GlFns::load_from(&|proc| SDL_GL_GetProcAddress(proc.cast())) }
+            .map_err(WindowError::FunctionLoading)?;
+
+        let renderer = Renderer::new(gl)?;
+
+        self.contexts.borrow_mut().push(Rc::downgrade(&renderer.gl));
 
         Ok(renderer)
     }
 
     pub fn new(title: &str, width: i32, height: i32) -> Result<Self, WindowError> {
+        WindowBuilder::new(title, width, height).build()
+    }
+
+    fn create(builder: &WindowBuilder) -> Result<Self, WindowError> {
         const ERROR_BUFFER_SIZE: c_int = 256;
 
+        let context_flags = if builder.debug_context {
+            SDL_GL_CONTEXT_DEBUG_FLAG.0
+        } else {
+            0
+        };
+
         let mut error_buffer = String::with_capacity(ERROR_BUFFER_SIZE as usize);
-        let title = CString::new(title).unwrap();
+        let title = CString::new(builder.title.as_str()).unwrap();
 
         let (window, gl_ctx) = {
             unsafe {
@@ -74,6 +123,8 @@ impl Window {
                         SDL_GL_CONTEXT_PROFILE_MASK,
                         SDL_GL_CONTEXT_PROFILE_CORE.0 as _,
                     )
+                    + SDL_GL_SetAttribute(SDL_GL_FRAMEBUFFER_SRGB_CAPABLE, 1)
+                    + SDL_GL_SetAttribute(SDL_GL_CONTEXT_FLAGS, context_flags as _)
                     != 0
                 {
                     SDL_GetErrorMsg(error_buffer.as_mut_ptr().cast(), ERROR_BUFFER_SIZE);
@@ -87,8 +138,8 @@ impl Window {
                     title.as_ptr().cast(),
                     SDL_WINDOWPOS_CENTERED,
                     SDL_WINDOWPOS_CENTERED,
-                    width,
-                    height,
+                    builder.width,
+                    builder.height,
                     (SDL_WINDOW_SHOWN | SDL_WINDOW_OPENGL | SDL_WINDOW_RESIZABLE).0,
                 )
             })
@@ -112,7 +163,11 @@ impl Window {
             (window, gl_ctx)
         };
 
-        Ok(Self { gl_ctx, window })
+        Ok(Self {
+            contexts: RefCell::default(),
+            gl_ctx,
+            window,
+        })
     }
     pub fn next_event(&self) -> Option<Event> {
         let mut event = SDL_Event::default();